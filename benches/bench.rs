@@ -1,15 +1,15 @@
 use bitonic_sort::bitonic_parallel;
 use bitonic_sort::bitonic_serial;
+use bitonic_sort::counting_sort;
+use bitonic_sort::odd_even_merge_sort;
 use bitonic_sort::parallel_sort;
-use criterion::{criterion_group, criterion_main, Criterion};
-use rand;
-use rand::Rng;
+use bitonic_sort::select;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+mod common;
 
 fn benchmark(c: &mut Criterion) {
-    let mut rng = rand::thread_rng();
-    let data: Vec<f64> = (0..1_000_000)
-        .map(|_| rng.gen_range(-1145141919.810..1145141919.810))
-        .collect();
+    let data = common::gen_uniform(1_000_000, common::SEED);
 
     c.bench_function("Parallel Sort", |b| {
         b.iter(|| {
@@ -17,18 +17,121 @@ fn benchmark(c: &mut Criterion) {
         })
     });
 
+    // more partitions means more merge passes, which used to allocate per worker per pass
+    let mut group = c.benchmark_group("Parallel Sort Merge Passes");
+    for parallel in [2, 16, 128] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(parallel),
+            &parallel,
+            |b, &parallel| {
+                b.iter(|| {
+                    parallel_sort::parallel_sort(&mut data.clone(), parallel);
+                })
+            },
+        );
+    }
+    group.finish();
+
     c.bench_function("Serial Bitonic Sort", |b| {
         b.iter(|| {
             bitonic_serial::bitonic_sort(&mut data.clone());
         })
     });
 
+    // bytes have so few values that counting them beats any comparison network
+    let bytes: Vec<u8> = data.iter().map(|&x| x as u8).collect();
+    let mut group = c.benchmark_group("Sort Bytes");
+    group.bench_function("counting_sort_u8", |b| {
+        b.iter(|| counting_sort::counting_sort_u8(&mut bytes.clone()))
+    });
+    group.bench_function("sort_small_domain", |b| {
+        b.iter(|| counting_sort::sort_small_domain(&mut bytes.clone()))
+    });
+    group.bench_function("bitonic_sort", |b| {
+        b.iter(|| bitonic_serial::bitonic_sort(&mut bytes.clone()))
+    });
+    group.finish();
+
+    c.bench_function("Serial Odd-Even Merge Sort", |b| {
+        b.iter(|| {
+            odd_even_merge_sort::odd_even_merge_sort(&mut data.clone());
+        })
+    });
+
     c.bench_function("Parallel Bitonic Sort", |b| {
         b.iter(|| {
             bitonic_parallel::bitonic_sort(&mut data.clone(), 16);
         })
     });
 
+    c.bench_function("Parallel Bitonic Sort Hybrid", |b| {
+        b.iter(|| {
+            bitonic_parallel::bitonic_sort_hybrid(&mut data.clone(), 16);
+        })
+    });
+
+    // medium inputs are where thread overhead on small subarrays dominates
+    let medium = &data[..65_536];
+    let mut group = c.benchmark_group("Parallel Bitonic Sort Cutoff");
+    for min_parallel_len in [0, 1024, bitonic_parallel::DEFAULT_MIN_PARALLEL_LEN, 16_384] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(min_parallel_len),
+            &min_parallel_len,
+            |b, &min_parallel_len| {
+                b.iter(|| {
+                    bitonic_parallel::bitonic_sort_with_min_len(
+                        &mut medium.to_vec(),
+                        16,
+                        min_parallel_len,
+                    );
+                })
+            },
+        );
+    }
+    group.finish();
+
+    #[cfg(feature = "simd")]
+    {
+        let data: Vec<f32> = data.iter().map(|&x| x as f32).collect();
+        let mut group = c.benchmark_group("Serial Bitonic Sort f32");
+        group.bench_function("scalar", |b| {
+            b.iter(|| bitonic_serial::bitonic_sort(&mut data.clone()))
+        });
+        group.bench_function("simd", |b| {
+            b.iter(|| bitonic_sort::bitonic_simd::bitonic_sort(&mut data.clone()))
+        });
+        group.finish();
+    }
+
+    // the median of a million floats, without and with sorting everything first
+    let mut group = c.benchmark_group("Select Median");
+    group.bench_function("parallel_select_nth", |b| {
+        b.iter(|| select::parallel_select_nth(&mut data.clone(), data.len() / 2, 8))
+    });
+    group.bench_function("parallel_sort", |b| {
+        b.iter(|| {
+            let mut sorted = data.clone();
+            parallel_sort::parallel_sort(&mut sorted, 8);
+            sorted[data.len() / 2]
+        })
+    });
+    group.finish();
+
+    // the same sort on inputs whose order the parallel merge sort's runs can exploit
+    let n = 1_000_000;
+    let mut group = c.benchmark_group("Parallel Sort Inputs");
+    for (name, input) in [
+        ("uniform", data.clone()),
+        ("sorted", common::gen_sorted(n)),
+        ("reverse sorted", common::gen_reverse_sorted(n)),
+        ("few unique", common::gen_few_unique(n, 16)),
+    ] {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &input, |b, input| {
+            b.iter(|| parallel_sort::parallel_sort(&mut input.clone(), 8))
+        });
+    }
+    group.finish();
+
     c.bench_function("Standard Library Sort", |b| {
         b.iter(|| {
             let mut cloned_data = data.clone();
@@ -0,0 +1,38 @@
+//! Input generators shared by the benchmarks.
+//!
+//! Every generator is deterministic, so runs compare against the same data, and own
+//! criterion groups can use the same inputs through `mod common;`.
+#![allow(dead_code)]
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The seed of the benchmarks' random inputs.
+pub const SEED: u64 = 0x5EED_B170_2C50;
+
+/// The range the random values are drawn from.
+const RANGE: std::ops::Range<f64> = -1145141919.810..1145141919.810;
+
+/// Returns `n` values drawn uniformly from a wide range, the same ones for the same
+/// `seed`.
+pub fn gen_uniform(n: usize, seed: u64) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n).map(|_| rng.gen_range(RANGE)).collect()
+}
+
+/// Returns `0, 1, ..., n - 1`.
+pub fn gen_sorted(n: usize) -> Vec<f64> {
+    (0..n).map(|x| x as f64).collect()
+}
+
+/// Returns `n - 1, ..., 1, 0`.
+pub fn gen_reverse_sorted(n: usize) -> Vec<f64> {
+    (0..n).rev().map(|x| x as f64).collect()
+}
+
+/// Returns `n` values drawn uniformly from `k` distinct ones, seeded with [`SEED`].
+pub fn gen_few_unique(n: usize, k: usize) -> Vec<f64> {
+    let values = gen_uniform(k, SEED ^ k as u64);
+    let mut rng = StdRng::seed_from_u64(SEED);
+    (0..n).map(|_| values[rng.gen_range(0..k)]).collect()
+}
@@ -0,0 +1,277 @@
+//! This module computes sorting permutations instead of reordering the input.
+//!
+//! The `bitonic_argsort` function returns the indices that would sort a slice, using the
+//! parallel bitonic network over `(value, original_index)` pairs. `rank_transform` turns
+//! those indices into the rank of every element.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::argsort::bitonic_argsort;
+//!
+//! let nums = [30, 10, 20];
+//! assert_eq!(bitonic_argsort(&nums, 2), vec![1, 2, 0]);
+//! ```
+use crate::bitonic_parallel;
+use crate::pool::ThreadPool;
+
+/// Returns the permutation of indices that sorts `nums` in ascending order.
+///
+/// Equal values are ordered by their original index, so the permutation is
+/// deterministic and stable regardless of `parallel`. Applying the permutation
+/// reproduces the sorted order: `perm.iter().map(|&i| nums[i])` yields the elements
+/// of `nums` in ascending order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::argsort::bitonic_argsort;
+///
+/// let nums = [2.5, 1.0, 2.5, 0.5];
+/// let perm = bitonic_argsort(&nums, 2);
+/// assert_eq!(perm, vec![3, 1, 0, 2]);
+///
+/// let sorted: Vec<f64> = perm.iter().map(|&i| nums[i]).collect();
+/// assert_eq!(sorted, vec![0.5, 1.0, 2.5, 2.5]);
+/// ```
+pub fn bitonic_argsort<T>(nums: &[T], parallel: usize) -> Vec<usize>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let mut pairs: Vec<(T, usize)> = nums.iter().copied().zip(0..).collect();
+    bitonic_parallel::bitonic_sort(&mut pairs, parallel);
+    pairs.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Writes the permutation of indices that sorts `nums` in ascending order into `out`.
+///
+/// This is [`bitonic_argsort`] without allocating the result, so the same buffer can be
+/// reused across argsorts of inputs of the same length. Instead of `(value, index)`
+/// pairs, the network sorts the indices in `out` directly, looking up the values for
+/// every comparison, and allocates nothing besides its threads. The permutation is the
+/// same, with equal values ordered by their index.
+///
+/// # Panics
+///
+/// Panics if `out.len() != nums.len()`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::argsort::bitonic_argsort_into;
+///
+/// let mut perm = [0; 4];
+/// bitonic_argsort_into(&[2.5, 1.0, 2.5, 0.5], &mut perm, 2);
+/// assert_eq!(perm, [3, 1, 0, 2]);
+/// bitonic_argsort_into(&[4, 3, 2, 1], &mut perm, 2);
+/// assert_eq!(perm, [3, 2, 1, 0]);
+/// ```
+pub fn bitonic_argsort_into<T>(nums: &[T], out: &mut [usize], parallel: usize)
+where
+    T: PartialOrd + Sync,
+{
+    assert_eq!(
+        nums.len(),
+        out.len(),
+        "the output must be as long as the input"
+    );
+    for (i, index) in out.iter_mut().enumerate() {
+        *index = i;
+    }
+    let parallel = bitonic_parallel::__effective_parallel(parallel, nums.len());
+    let pool = ThreadPool::for_parallel(parallel);
+    let cmp = |&i: &usize, &j: &usize| bitonic_parallel::__cmp(&nums[i], &nums[j]).then(i.cmp(&j));
+    bitonic_parallel::__bitonic_sort(out, false, &cmp, parallel, 0, &pool, None);
+}
+
+/// Reorders `data` by a permutation, so that `data[i]` becomes the element that was at
+/// `perm[i]`.
+///
+/// This applies the output of [`bitonic_argsort`] to any slice, so that several columns
+/// of a table can be sorted by one of them. The elements are swapped along the cycles of
+/// the permutation, which needs no buffer and no `Copy` or `Clone` bound. `perm` is used
+/// to mark the visited indices and holds the same permutation again on return, ready
+/// for the next column.
+///
+/// # Panics
+///
+/// Panics if `perm` is not a permutation of `0..data.len()`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::argsort::{apply_permutation_in_place, bitonic_argsort};
+///
+/// let mut ids = vec![7, 3, 9];
+/// let mut scores = vec![2.5, 0.5, 1.5];
+/// let mut names = vec![String::from("carol"), String::from("alice"), String::from("bob")];
+///
+/// let mut perm = bitonic_argsort(&scores, 2);
+/// apply_permutation_in_place(&mut ids, &mut perm);
+/// apply_permutation_in_place(&mut scores, &mut perm);
+/// apply_permutation_in_place(&mut names, &mut perm);
+/// assert_eq!(ids, vec![3, 9, 7]);
+/// assert_eq!(scores, vec![0.5, 1.5, 2.5]);
+/// assert_eq!(names, vec!["alice", "bob", "carol"]);
+/// ```
+pub fn apply_permutation_in_place<T>(data: &mut [T], perm: &mut [usize]) {
+    let len = data.len();
+    assert_eq!(
+        len,
+        perm.len(),
+        "the permutation must be as long as the data"
+    );
+    // a visited index holds the complement of its entry, which cannot be mistaken for an
+    // entry: `perm` is a slice of `usize`, so `len` is far below `usize::MAX / 2`
+    for start in 0..len {
+        if perm[start] >= len {
+            continue;
+        }
+        let mut current = start;
+        loop {
+            let next = perm[current];
+            assert!(next < len, "not a permutation");
+            perm[current] = !next;
+            if next == start {
+                break;
+            }
+            data.swap(current, next);
+            current = next;
+        }
+    }
+    for entry in perm.iter_mut() {
+        *entry = !*entry;
+    }
+}
+
+/// Returns the 0-based rank of every element of `nums`, its position in sorted order.
+///
+/// Ties get the average rank: a run of `k` equal elements that would occupy the sorted
+/// positions `p..p + k` all get `p + (k - 1) / 2`, so the ranks of `[10, 20, 20, 30]` are
+/// `[0.0, 1.5, 1.5, 3.0]` and the ranks always sum to `n * (n - 1) / 2`, as for distinct
+/// elements. This is the rank transform of the Spearman correlation, shifted by one.
+/// Elements are tied if they compare equal; an incomparable element such as `NaN` ties
+/// with nothing, and its rank is unspecified.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::argsort::rank_transform;
+///
+/// let nums = [3.5, 1.0, 3.5, 0.5, 3.5];
+/// assert_eq!(rank_transform(&nums, 2), vec![3.0, 1.0, 3.0, 0.0, 3.0]);
+/// ```
+pub fn rank_transform<T>(nums: &[T], parallel: usize) -> Vec<f64>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let perm = bitonic_argsort(nums, parallel);
+    let mut ranks = vec![0.0; nums.len()];
+    let mut start = 0;
+    while start < perm.len() {
+        // the tied run is `perm[start..end]`
+        let mut end = start + 1;
+        while end < perm.len() && nums[perm[end]] == nums[perm[start]] {
+            end += 1;
+        }
+        let rank = (start + end - 1) as f64 / 2.0;
+        for &i in &perm[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_bitonic_argsort() {
+        let nums = [4, 2, 7, 1, 5, 3, 6];
+        assert_eq!(bitonic_argsort(&nums, 2), vec![3, 1, 5, 0, 4, 6, 2]);
+    }
+
+    #[test]
+    fn test_bitonic_argsort_empty() {
+        let nums: [i32; 0] = [];
+        assert_eq!(bitonic_argsort(&nums, 2), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_bitonic_argsort_ties_by_index() {
+        let nums = [1, 0, 1, 0, 1, 0];
+        for parallel in [1, 2, 4] {
+            assert_eq!(bitonic_argsort(&nums, parallel), vec![1, 3, 5, 0, 2, 4]);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_argsort_reproduces_sorted_order() {
+        let nums = [4, 2, 7, 1, 5, 3, 6, 4, 2, 7];
+        let sorted: Vec<i32> = bitonic_argsort(&nums, 4).iter().map(|&i| nums[i]).collect();
+        assert_eq!(sorted, vec![1, 2, 2, 3, 4, 4, 5, 6, 7, 7]);
+    }
+
+    #[test]
+    fn test_rank_transform_distinct() {
+        let nums = [40, 10, 30, 20];
+        assert_eq!(rank_transform(&nums, 2), vec![3.0, 0.0, 2.0, 1.0]);
+        assert_eq!(rank_transform::<i32>(&[], 2), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn test_rank_transform_ties() {
+        // runs of two at positions 1..3 and of three at positions 4..7
+        let nums = [5, 2, 9, 2, 1, 9, 7, 9];
+        let ranks = rank_transform(&nums, 4);
+        assert_eq!(ranks, vec![3.0, 1.5, 6.0, 1.5, 0.0, 6.0, 4.0, 6.0]);
+        assert_eq!(ranks.iter().sum::<f64>(), (8 * 7 / 2) as f64);
+        assert_eq!(rank_transform(&[7; 4], 2), vec![1.5; 4]);
+    }
+
+    #[test]
+    fn test_apply_permutation_in_place() {
+        let scores = gen_few_unique(200, 50);
+        let mut names: Vec<String> = scores.iter().map(|x| format!("name-{}", x)).collect();
+        let mut ids: Vec<usize> = (0..200).collect();
+        let mut perm = bitonic_argsort(&scores, 4);
+        let expected_perm = perm.clone();
+        apply_permutation_in_place(&mut ids, &mut perm);
+        assert_eq!(perm, expected_perm);
+        assert_eq!(ids, expected_perm);
+        apply_permutation_in_place(&mut names, &mut perm);
+        let expected: Vec<String> = perm
+            .iter()
+            .map(|&i| format!("name-{}", scores[i]))
+            .collect();
+        assert_eq!(names, expected);
+        apply_permutation_in_place::<u8>(&mut [], &mut []);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a permutation")]
+    fn test_apply_permutation_with_duplicates() {
+        apply_permutation_in_place(&mut [1, 2, 3], &mut [1, 1, 0]);
+    }
+
+    #[test]
+    fn test_bitonic_argsort_into_matches_argsort() {
+        let mut out = vec![usize::MAX; 1000];
+        for len in [0, 1, 7, 513, 1000] {
+            for parallel in [1, 3, 8] {
+                let nums = gen_few_unique(len, 100);
+                bitonic_argsort_into(&nums, &mut out[..len], parallel);
+                assert_eq!(out[..len], bitonic_argsort(&nums, parallel)[..]);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the output must be as long as the input")]
+    fn test_bitonic_argsort_into_short_output() {
+        bitonic_argsort_into(&[3, 1, 2], &mut [0; 2], 2);
+    }
+}
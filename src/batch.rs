@@ -0,0 +1,82 @@
+//! This module sorts many independent vectors at once.
+//!
+//! The other parallel sorts split a single input across threads, which only pays off
+//! for long inputs. For thousands of short vectors, the parallelism is across the
+//! inputs instead: each vector is sorted serially by one thread, and the threads take
+//! vectors off a shared queue until none are left, so a few long vectors do not hold up
+//! the rest.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::batch::sort_batch;
+//!
+//! let mut batches = vec![vec![3, 1, 2], vec![9, 7], vec![], vec![5, 4, 6, 0]];
+//! sort_batch(&mut batches, 2);
+//! assert_eq!(batches, vec![vec![1, 2, 3], vec![7, 9], vec![], vec![0, 4, 5, 6]]);
+//! ```
+use crate::bitonic_serial;
+use crate::pool;
+use std::sync::Mutex;
+use std::thread;
+
+/// Sorts every vector of `batches` in ascending order, using up to `threads` threads.
+///
+/// Each vector is sorted serially with [`bitonic_serial::bitonic_sort`] on a single
+/// thread. The calling thread is one of the `threads`; zero and one both sort every
+/// vector on it, and no more threads are started than there are vectors.
+pub fn sort_batch<T>(batches: &mut [Vec<T>], threads: usize)
+where
+    T: PartialOrd + Send,
+{
+    let threads = threads.clamp(1, batches.len().max(1));
+    let queue = Mutex::new(batches.iter_mut());
+    let work = || loop {
+        // the lock is only held to take the next vector, not while sorting it
+        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+        match next {
+            Some(nums) => bitonic_serial::bitonic_sort(nums),
+            None => break,
+        }
+    };
+    thread::scope(|s| {
+        for _ in 1..threads {
+            pool::spawn_named(s, "bitonic-batch", work);
+        }
+        work();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{gen_uniform, SEED};
+
+    #[test]
+    fn test_sort_batch() {
+        let batches: Vec<Vec<u32>> = (0..1000u64)
+            .map(|i| {
+                gen_uniform((i % 37) as usize, SEED + i)
+                    .into_iter()
+                    .map(|x| x % 100)
+                    .collect()
+            })
+            .collect();
+        for threads in [0, 1, 3, 8] {
+            let mut sorted = batches.clone();
+            sort_batch(&mut sorted, threads);
+            for (x, (a, b)) in sorted.iter().zip(&batches).enumerate() {
+                let mut expected = b.clone();
+                expected.sort();
+                assert!(*a == expected, "batch {} with {} threads", x, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sort_batch_empty() {
+        let mut batches: Vec<Vec<f64>> = Vec::new();
+        sort_batch(&mut batches, 4);
+        assert!(batches.is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! This module sorts one-dimensional `ndarray` views with the serial bitonic network,
+//! behind the `ndarray` feature.
+//!
+//! A view may be strided, e.g. every other element of an array or a column of a matrix,
+//! in which case its elements are not a slice. Such views are sorted by indexing through
+//! the view, running the same comparators as [`bitonic_serial`] in the same order.
+//! Contiguous views are sorted as a slice.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::bitonic_ndarray::bitonic_sort_ndarray;
+//! use ndarray::{array, s};
+//!
+//! let mut nums = array![4, 0, 2, 0, 7, 0, 1];
+//! bitonic_sort_ndarray(&mut nums.slice_mut(s![..;2]));
+//! assert_eq!(nums, array![1, 0, 2, 0, 4, 0, 7]);
+//! ```
+
+use crate::bitonic_serial::{self, __should_swap};
+use core::cmp::Ordering;
+use ndarray::ArrayViewMut1;
+
+/// Sorts the elements of the given view in ascending order, in place.
+///
+/// Like [`bitonic_serial::bitonic_sort`], incomparable elements such as `NaN` are never
+/// swapped. The view may have any stride, including a negative one.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_ndarray::bitonic_sort_ndarray;
+/// use ndarray::{array, s};
+///
+/// // the second column of a matrix
+/// let mut matrix = array![[0, 3], [0, 1], [0, 2]];
+/// bitonic_sort_ndarray(&mut matrix.slice_mut(s![.., 1]));
+/// assert_eq!(matrix, array![[0, 1], [0, 2], [0, 3]]);
+/// ```
+pub fn bitonic_sort_ndarray<T>(view: &mut ArrayViewMut1<'_, T>)
+where
+    T: PartialOrd,
+{
+    if let Some(nums) = view.as_slice_mut() {
+        bitonic_serial::bitonic_sort_slice(nums);
+        return;
+    }
+    bitonic_serial::__for_each_comparator(view.len(), false, |i, j, reverse| {
+        let ord = view[i].partial_cmp(&view[j]).unwrap_or(Ordering::Equal);
+        if __should_swap(ord, reverse) {
+            view.swap(i, j);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+    use ndarray::{s, Array1};
+
+    #[test]
+    fn test_bitonic_sort_ndarray_contiguous() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            let mut array = Array1::from(nums);
+            bitonic_sort_ndarray(&mut array.view_mut());
+            assert!(
+                array.to_vec() == expected,
+                "unsorted output for len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_ndarray_strided() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            for step in [2isize, 3, -1, -2] {
+                let mut array = Array1::from(nums.clone());
+                let mut expected = array.slice(s![..;step]).to_vec();
+                expected.sort();
+                bitonic_sort_ndarray(&mut array.slice_mut(s![..;step]));
+                assert!(
+                    array.slice(s![..;step]).to_vec() == expected,
+                    "unsorted output for len = {}, step = {}",
+                    len,
+                    step
+                );
+                // the elements between the strides stay where they are
+                let untouched = (0..nums.len()).filter(|&i| match step {
+                    step if step > 0 => !i.is_multiple_of(step.unsigned_abs()),
+                    step => !(nums.len() - 1 - i).is_multiple_of(step.unsigned_abs()),
+                });
+                for i in untouched {
+                    assert_eq!(array[i], nums[i], "moved element {} for step = {}", i, step);
+                }
+            }
+        }
+    }
+}
@@ -13,10 +13,44 @@
 //! bitonic_sort(&mut nums, parallel);
 //! assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
 //! ```
-/// This module contains the implementation of a parallel bitonic sort algorithm.
+//!
+//! # Stability
+//!
+//! [`sort`] is stable and [`sort_unstable`] is not, like their namesakes in the standard
+//! library. `sort_unstable` may reorder equal elements and works in place; `sort` sorts
+//! `(element, index)` pairs in a buffer of `n` of them. Every other sort of this module
+//! is unstable.
+//!
+//! # Threads
+//!
+//! The recursive halves of the sort and the slices of every merge pass are jobs on one
+//! [`ThreadPool`] per call, not threads of their own. A sort with a `parallel` of `p`,
+//! rounded up to a power of two, starts `p - 1` workers and works on the calling thread
+//! too, so at most `p` threads ever run its comparisons, however deep the recursion
+//! nests. A thread waiting for nested jobs runs queued ones itself instead of blocking.
+//! The `*_with_pool` sorts use the borrowed pool's workers plus the calling thread
+//! instead.
+
+// the `Vec` entry points mirror `parallel_sort` even though this network handles every
+// length in place
+#![allow(clippy::ptr_arg)]
+
+use crate::bitonic_serial;
+use crate::config::{Algorithm, SortConfig};
+use crate::error::SortError;
+use crate::pool::ThreadPool;
+use crate::validate;
+use std::cmp::Ordering;
+use std::mem;
+use std::ops::Range;
+use std::sync::atomic::{self, AtomicBool};
+
+/// Sorts the given vector in ascending order, splitting the work into `parallel` parts.
 ///
-/// The `bitonic_sort` function sorts a given vector in ascending order using the bitonic sort algorithm.
-/// It supports parallel execution by dividing the sorting process into multiple threads.
+/// A `parallel` of `0` or `1` sorts serially on the calling thread. Other values are
+/// rounded up to the next power of two, so `3` sorts with four threads and `255` with
+/// 256. The effective parallelism is `min(requested, len / 2)`, rounded down to a power
+/// of two, so that no thread gets fewer than two elements.
 ///
 /// # Examples
 ///
@@ -28,122 +62,807 @@
 /// bitonic_sort(&mut nums, parallel);
 /// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
 /// ```
+pub fn bitonic_sort<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    SortConfig::new().parallel(parallel).build().sort(nums);
+}
+
+/// Sorts the given vector like [`bitonic_sort`], keeping equal elements in their input
+/// order.
+///
+/// The elements are sorted as `(element, index)` pairs whose ties are broken by the
+/// index, in a buffer of `n` such pairs, and copied back. The guarantee covers elements
+/// that compare as equal; incomparable ones such as `NaN` have no defined place.
 ///
-use std::cell::Cell;
-use std::sync::Arc;
-use std::{mem, slice, thread};
-struct SliceWrapper<T: ?Sized>(*mut T);
-unsafe impl<T> Send for SliceWrapper<T> {}
-unsafe impl<T> Sync for SliceWrapper<T> {}
-impl<T> Clone for SliceWrapper<T> {
-    fn clone(&self) -> Self {
-        Self(self.0)
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::sort;
+///
+/// // sort by the first field only, through a wrapper that ignores the second
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct ByDay(u32, char);
+///
+/// impl PartialOrd for ByDay {
+///     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+///         self.0.partial_cmp(&other.0)
+///     }
+/// }
+///
+/// let mut events = vec![ByDay(2, 'a'), ByDay(1, 'b'), ByDay(2, 'c'), ByDay(1, 'd')];
+/// sort(&mut events, 2);
+/// let tags: String = events.iter().map(|event| event.1).collect();
+/// assert_eq!(tags, "bdac");
+/// ```
+pub fn sort<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let mut pairs: Vec<(T, usize)> = nums.iter().copied().zip(0..).collect();
+    let parallel = __effective_parallel(parallel, nums.len());
+    let pool = ThreadPool::for_parallel(parallel);
+    __bitonic_sort(
+        &mut pairs[..],
+        false,
+        &|a: &(T, usize), b: &(T, usize)| __cmp(&a.0, &b.0).then(a.1.cmp(&b.1)),
+        parallel,
+        0,
+        &pool,
+        None,
+    );
+    for (num, (x, _)) in nums.iter_mut().zip(pairs) {
+        *num = x;
     }
 }
-impl<T> Copy for SliceWrapper<T> {}
 
-pub fn bitonic_sort<T>(nums: &mut Vec<T>, mut parallel: u8)
+/// Sorts the given vector in ascending order without keeping equal elements in their
+/// input order.
+///
+/// This is [`bitonic_sort`] under the standard library's name: it sorts in place and
+/// only allocates the threads.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::sort_unstable;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// sort_unstable(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn sort_unstable<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    bitonic_sort(nums, parallel);
+}
+
+/// Sorts the given vector like [`bitonic_sort`], running the work on a borrowed pool.
+///
+/// The pool's threads are reused across calls, which avoids the thread creation cost
+/// when sorting many small inputs. `parallel` still controls how the input is split;
+/// the number of threads doing the work is the pool size plus the calling thread.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_with_pool;
+/// use bitonic_sort::pool::ThreadPool;
+///
+/// let pool = ThreadPool::new(1);
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort_with_pool(&mut nums, 2, &pool);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_with_pool<T>(nums: &mut Vec<T>, parallel: usize, pool: &ThreadPool)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    __bitonic_sort(&mut nums[..], false, &__cmp, parallel, 0, pool, None);
+}
+
+/// A starting point for the `min_parallel_len` of [`bitonic_sort_with_min_len`].
+///
+/// Below a few thousand elements, spawning work onto other threads costs more than
+/// sorting serially. The best value depends on the machine, so measure before relying
+/// on it.
+pub const DEFAULT_MIN_PARALLEL_LEN: usize = 4096;
+
+/// Sorts the given vector like [`bitonic_sort`], but stops splitting work across threads
+/// once a subarray is shorter than `min_parallel_len`.
+///
+/// Subarrays below the threshold are sorted and merged serially even if parallel budget
+/// remains, which avoids paying the thread overhead for work too small to benefit from
+/// it. A `min_parallel_len` of zero keeps the behavior of [`bitonic_sort`].
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::{bitonic_sort_with_min_len, DEFAULT_MIN_PARALLEL_LEN};
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort_with_min_len(&mut nums, 8, DEFAULT_MIN_PARALLEL_LEN);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_with_min_len<T>(nums: &mut Vec<T>, parallel: usize, min_parallel_len: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    SortConfig::new()
+        .parallel(parallel)
+        .min_parallel_len(min_parallel_len)
+        .build()
+        .sort(nums);
+}
+
+/// Sorts the given vector like [`bitonic_sort`], but sorts the parts of the single
+/// threads with the standard library's `sort_unstable` instead of the network.
+///
+/// The vector is split into parts as in [`bitonic_sort`]. Each part is sorted in
+/// O(m log m) rather than the network's O(m log^2 m), and the sorted parts are combined
+/// by the parallel bitonic merges, which makes this a parallel merge sort with bitonic
+/// merges. It is faster the fewer threads there are per element. Like the standard
+/// library's sort, it may panic if two elements are not comparable, e.g. a `NaN` float.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_hybrid;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5, 3, 6];
+/// bitonic_sort_hybrid(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+/// ```
+pub fn bitonic_sort_hybrid<T>(nums: &mut Vec<T>, parallel: usize)
 where
     T: PartialOrd + Copy + Send + Sync,
 {
-    if nums.is_empty() {
+    let parallel = __effective_parallel(parallel, nums.len());
+    SortConfig::new()
+        .parallel(parallel)
+        .algorithm(Algorithm::Hybrid)
+        .build()
+        .sort(nums);
+}
+
+/// Returns a sorted copy of `nums`, leaving the input untouched.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sorted;
+///
+/// let nums = [4, 2, 7, 1, 5];
+/// assert_eq!(bitonic_sorted(&nums, 2), vec![1, 2, 4, 5, 7]);
+/// assert_eq!(nums, [4, 2, 7, 1, 5]);
+/// ```
+pub fn bitonic_sorted<T>(nums: &[T], parallel: usize) -> Vec<T>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let mut sorted = nums.to_vec();
+    bitonic_sort(&mut sorted, parallel);
+    sorted
+}
+
+/// Collects `iter` into a vector and sorts it like [`bitonic_sort`].
+///
+/// This is the entry point for data that comes out of an iterator chain rather than an
+/// existing vector; [`BitonicIteratorExt`](crate::ext::BitonicIteratorExt) offers it as a
+/// method.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::into_sorted_vec;
+///
+/// let evens = into_sorted_vec([9, 4, 7, 2, 8].into_iter().filter(|x| x % 2 == 0), 2);
+/// assert_eq!(evens, vec![2, 4, 8]);
+/// ```
+pub fn into_sorted_vec<I, T>(iter: I, parallel: usize) -> Vec<T>
+where
+    I: IntoIterator<Item = T>,
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let mut sorted: Vec<T> = iter.into_iter().collect();
+    bitonic_sort(&mut sorted, parallel);
+    sorted
+}
+
+/// Sorts the given vector in descending order.
+///
+/// The network just runs in the opposite direction. It handles every length without
+/// padding, so no minimum element has to be found and nothing needs to be truncated.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_desc;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort_desc(&mut nums, 2);
+/// assert_eq!(nums, vec![7, 5, 4, 2, 1]);
+/// ```
+pub fn bitonic_sort_desc<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    SortConfig::new()
+        .parallel(parallel)
+        .reverse(true)
+        .build()
+        .sort(nums);
+}
+
+/// Sorts the given vector like [`bitonic_sort`], but takes a shortcut for input that
+/// is already mostly in order.
+///
+/// One O(n) scan finds the longest sorted prefix. If that is the whole vector, nothing
+/// else happens; equal neighbours count as sorted, so a constant vector, such as a
+/// default-filled column, costs `n - 1` comparisons and no threads. Otherwise only the
+/// tail after the prefix runs through the full network; the prefix is reversed, which
+/// makes it and the sorted tail a single bitonic sequence, and one merge pass of the
+/// network combines them. For data that is appended to and sorted again, with a tail of
+/// `t` new elements, this costs O(n log n + t log^2 t) instead of O(n log^2 n).
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_adaptive;
+///
+/// let mut nums = vec![1, 3, 4, 6, 8, 9, 5, 2];
+/// bitonic_sort_adaptive(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 8, 9]);
+/// ```
+pub fn bitonic_sort_adaptive<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let len = nums.len();
+    let sorted = 1 + nums
+        .windows(2)
+        .take_while(|w| __cmp(&w[0], &w[1]) != Ordering::Greater)
+        .count();
+    if sorted >= len {
         return;
     }
-    parallel = parallel.checked_next_power_of_two().unwrap_or(u8::MAX);
-    let origin_len = nums.len();
-    if !origin_len.is_power_of_two() {
-        let max = *nums.iter().fold(
-            nums.first().unwrap(),
-            |max, x| if max > x { max } else { x },
-        );
-        nums.resize(origin_len.next_power_of_two(), max);
+    let parallel = __effective_parallel(parallel, len);
+    let pool = ThreadPool::for_parallel(parallel);
+    let (head, tail) = nums.split_at_mut(sorted);
+    head.reverse();
+    __bitonic_sort(tail, false, &__cmp, parallel, 0, &pool, None);
+    __bitonic_merge(&mut nums[..], false, &__cmp, parallel, 0, &pool, None);
+}
+
+/// Sorts the given vector using a thread count derived from the machine.
+///
+/// The degree of parallelism is the largest power of two not exceeding
+/// `std::thread::available_parallelism()`, further capped so that no thread gets fewer
+/// than two elements. If the core count cannot be determined the vector is sorted
+/// with [`bitonic_serial::bitonic_sort`] instead.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_auto;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort_auto(&mut nums);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_auto<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    match crate::auto_parallel(nums.len()) {
+        Some(parallel) => bitonic_sort(nums, parallel),
+        None => bitonic_serial::bitonic_sort(nums),
     }
-    __bitonic_sort(&mut nums[..], false, parallel);
-    nums.truncate(origin_len);
 }
 
-fn __bitonic_merge<T>(nums: &mut [T], reverse: bool, mut parallel: u8)
+/// Sorts the given slice in ascending order, for callers that do not own a `Vec`.
+///
+/// The network handles every length directly, so the slice is sorted in place
+/// without a scratch buffer.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_slice;
+///
+/// let mut nums = [4, 2, 7, 1, 5];
+/// bitonic_sort_slice(&mut nums, 2);
+/// assert_eq!(nums, [1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_slice<T>(nums: &mut [T], parallel: usize)
 where
     T: PartialOrd + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    __bitonic_sort(
+        nums,
+        false,
+        &__cmp,
+        parallel,
+        0,
+        &ThreadPool::for_parallel(parallel),
+        None,
+    );
+}
+
+/// Sorts only the elements of `nums` within `range`, leaving the rest untouched.
+///
+/// The window is sorted in place like [`bitonic_sort_slice`]; the network needs no
+/// padding, so the vector never grows and no element outside the window moves.
+///
+/// # Panics
+///
+/// Panics if `range` is out of bounds for `nums`, as slicing would.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_range;
+///
+/// let mut nums = vec![9, 4, 2, 7, 1, 0];
+/// bitonic_sort_range(&mut nums, 1..5, 2);
+/// assert_eq!(nums, vec![9, 1, 2, 4, 7, 0]);
+/// ```
+pub fn bitonic_sort_range<T>(nums: &mut Vec<T>, range: Range<usize>, parallel: usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    bitonic_sort_slice(&mut nums[range], parallel);
+}
+
+/// Sorts the given vector in parallel with a comparator chosen at runtime.
+///
+/// The comparator is shared by all threads, so the trait object has to be `Sync`. Every
+/// comparison is a virtual call; the sort itself is compiled once for `T`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_dyn;
+/// use std::cmp::Ordering;
+///
+/// let by_len: Box<dyn Fn(&&str, &&str) -> Ordering + Sync> =
+///     Box::new(|a, b| a.len().cmp(&b.len()));
+/// let mut words = vec!["pear", "fig", "banana", "apple"];
+/// bitonic_sort_dyn(&mut words, 2, &*by_len);
+/// assert_eq!(words, vec!["fig", "pear", "apple", "banana"]);
+/// ```
+pub fn bitonic_sort_dyn<T>(
+    nums: &mut Vec<T>,
+    parallel: usize,
+    cmp: &(dyn Fn(&T, &T) -> Ordering + Sync),
+) where
+    T: Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    let pool = ThreadPool::for_parallel(parallel);
+    __bitonic_sort(&mut nums[..], false, &cmp, parallel, 0, &pool, None);
+}
+
+/// Sorts the given vector of totally ordered elements in parallel.
+///
+/// This compares with `Ord::cmp` directly, so unlike [`bitonic_sort`] no pair of
+/// elements can be incomparable. See [`bitonic_serial::bitonic_sort_ord`] for element
+/// types that are not `Copy`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_ord;
+///
+/// let mut nums = vec![4u64, 2, 7, 1, 5, 3, 6];
+/// bitonic_sort_ord(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+/// ```
+pub fn bitonic_sort_ord<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: Ord + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    let pool = ThreadPool::for_parallel(parallel);
+    __bitonic_sort(&mut nums[..], false, &T::cmp, parallel, 0, &pool, None);
+    validate::__debug_assert_sorted(nums, false, &T::cmp);
+}
+
+/// Sorts the given vector like [`bitonic_sort`], giving up early once `cancel` is set.
+///
+/// The flag is polled before every merge pass, so a sort notices a cancellation within
+/// one pass over its current subarray. If the flag is set by the time the sort returns,
+/// the result is [`SortError::Cancelled`] and the vector holds its original elements
+/// in an unspecified order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_cancellable;
+/// use bitonic_sort::error::SortError;
+/// use std::sync::atomic::AtomicBool;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// assert_eq!(bitonic_sort_cancellable(&mut nums, 2, &AtomicBool::new(false)), Ok(()));
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+///
+/// let cancel = AtomicBool::new(true);
+/// assert_eq!(bitonic_sort_cancellable(&mut nums, 2, &cancel), Err(SortError::Cancelled));
+/// ```
+pub fn bitonic_sort_cancellable<T>(
+    nums: &mut Vec<T>,
+    parallel: usize,
+    cancel: &AtomicBool,
+) -> Result<(), SortError>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let parallel = __effective_parallel(parallel, nums.len());
+    let pool = ThreadPool::for_parallel(parallel);
+    __bitonic_sort(
+        &mut nums[..],
+        false,
+        &__cmp,
+        parallel,
+        0,
+        &pool,
+        Some(cancel),
+    );
+    if cancel.load(atomic::Ordering::Relaxed) {
+        return Err(SortError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Sorts the given vector like [`bitonic_sort`], reporting progress after every pass.
+///
+/// Instead of recursing, the network is run one pass at a time from the calling thread:
+/// every pass applies the compare-exchanges of all subarrays at the same point of the
+/// network, split across `parallel` threads. The first passes of the last merge have a
+/// single subarray, whose compare-exchanges are split into chunks for the threads like
+/// those of [`bitonic_sort`]'s merges. After each pass `progress` is called on
+/// the calling thread with the fraction of the network's compare-exchanges done so far,
+/// so it does not have to be `Send`. The last call reports `1.0`; inputs with fewer
+/// than two elements need no pass and report nothing.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_parallel::bitonic_sort_with_progress;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5, 3, 6, 8];
+/// let mut reports = Vec::new();
+/// bitonic_sort_with_progress(&mut nums, 2, |done| reports.push(done));
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+/// assert_eq!(reports.len(), 6);
+/// assert_eq!(reports.last(), Some(&1.0));
+/// ```
+pub fn bitonic_sort_with_progress<T, F>(nums: &mut Vec<T>, parallel: usize, mut progress: F)
+where
+    T: PartialOrd + Copy + Send + Sync,
+    F: FnMut(f32),
 {
     let len = nums.len();
-    if parallel <= 1 {
-        let slice = Cell::from_mut(&mut nums[..]).as_slice_of_cells();
-        for (num1, num2) in slice[..len / 2].iter().zip(slice[len / 2..].iter()) {
-            if (num1.get() > num2.get()) ^ reverse {
-                Cell::swap(num1, num2);
+    let parallel = __effective_parallel(parallel, nums.len());
+    let pool = ThreadPool::for_parallel(parallel);
+    let total = __sort_len(len);
+    let mut done = 0;
+    // subarrays `depth` levels down are at most `len / 2^depth` long, rounded up, so the
+    // deepest ones that still need a merge are `log2(len) - 1` levels down. They are
+    // merged first, and each merge takes one pass per level of its own recursion, until
+    // a level has no subarray left to merge.
+    let levels = usize::BITS - (len.max(1) - 1).leading_zeros();
+    for depth in (0..levels as usize).rev() {
+        for level in 0.. {
+            let pass = __sort_pass(&mut nums[..], false, depth, level, parallel, &pool);
+            if pass == 0 {
+                break;
             }
+            done += pass;
+            progress(done as f32 / total as f32);
         }
+    }
+}
+
+// Like `bitonic_serial`, the network works on any length: the first comparator stride is
+// the largest power of two below `len` and only the first `len - stride` elements have a
+// partner, so nothing needs to be padded. The first pass is split across `parallel`
+// threads.
+fn __bitonic_merge<T, F>(
+    nums: &mut [T],
+    reverse: bool,
+    cmp: &F,
+    parallel: usize,
+    min_len: usize,
+    pool: &ThreadPool,
+    cancel: Option<&AtomicBool>,
+) where
+    T: Copy + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let len = nums.len();
+    if len <= 1 || __cancelled(cancel) {
         return;
     }
-    let mut size = len / (2 * parallel as usize);
-    if size == 0 {
-        parallel = (len / 2) as u8;
-        size = 1;
-    }
-    let shared_nums = Arc::new(SliceWrapper(nums.as_mut_ptr()));
-    thread::scope(|s| {
-        for i in 0..parallel as usize {
-            let nums = Arc::clone(&shared_nums);
-            s.spawn(move || {
-                let slice1 = unsafe {
-                    slice::from_raw_parts_mut(nums.0, len)
-                        .get_unchecked_mut(i * size..(i + 1) * size)
-                };
-                let slice2 = unsafe {
-                    slice::from_raw_parts_mut(nums.0, len)
-                        .get_unchecked_mut(len / 2 + i * size..len / 2 + (i + 1) * size)
-                };
-                for (num1, num2) in slice1.iter_mut().zip(slice2.iter_mut()) {
-                    if (num1 > num2) ^ reverse {
-                        mem::swap(num1, num2);
-                    }
-                }
-            });
+    if __serial(len, parallel, min_len, cancel) {
+        bitonic_serial::__bitonic_merge(nums, reverse, cmp);
+        return;
+    }
+    let stride = bitonic_serial::__stride(len);
+    let pairs = len - stride;
+    let size = pairs.div_ceil(parallel.max(1));
+    let (lo, hi) = nums.split_at_mut(stride);
+    pool.scope(|s| {
+        for (lo, hi) in lo[..pairs].chunks_mut(size).zip(hi.chunks_mut(size)) {
+            s.spawn(move || __half_clean(lo, hi, reverse, cmp));
         }
-    })
+    });
+    // the halves are independent, so they are merged side by side with half the threads
+    // each, like the halves of the sort. Merging them one after the other with all
+    // threads would open a scope for every subarray down to single elements.
+    let (lo, hi) = nums.split_at_mut(stride);
+    pool.scope(|s| {
+        s.spawn(|| __bitonic_merge(lo, reverse, cmp, parallel / 2, min_len, pool, cancel));
+        s.spawn(|| __bitonic_merge(hi, reverse, cmp, parallel / 2, min_len, pool, cancel));
+    });
 }
 
-fn __bitonic_sort<T>(nums: &mut [T], reverse: bool, parallel: u8)
-where
-    T: PartialOrd + Copy + Send + Sync,
+pub(crate) fn __bitonic_sort<T, F>(
+    nums: &mut [T],
+    reverse: bool,
+    cmp: &F,
+    parallel: usize,
+    min_len: usize,
+    pool: &ThreadPool,
+    cancel: Option<&AtomicBool>,
+) where
+    T: Copy + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let len = nums.len();
+    if len <= 1 || __cancelled(cancel) {
+        return;
+    }
+    // halved at every level, so anything else would desync from the halves of `nums`;
+    // a cancellable sort keeps halving a serial `1` down to `0`
+    debug_assert!(
+        parallel <= 1 || parallel.is_power_of_two(),
+        "`parallel` is not a power of two"
+    );
+    if __serial(len, parallel, min_len, cancel) {
+        bitonic_serial::__bitonic_sort(nums, reverse, cmp);
+        return;
+    }
+    // the `2^d` subarrays `d` levels down are sorted and merged side by side with
+    // `parallel / 2^d` threads each, so once a subarray is down to one thread it runs
+    // serially next to the others instead of all threads visiting each in turn
+    let (lo, hi) = nums.split_at_mut(len / 2);
+    pool.scope(|s| {
+        s.spawn(|| __bitonic_sort(lo, !reverse, cmp, parallel / 2, min_len, pool, cancel));
+        s.spawn(|| __bitonic_sort(hi, reverse, cmp, parallel / 2, min_len, pool, cancel));
+    });
+    __bitonic_merge(nums, reverse, cmp, parallel, min_len, pool, cancel);
+}
+
+// Splits like `__bitonic_sort`, but sorts the parts of single threads with
+// `sort_unstable_by`. A part sorted in its direction is what the network would have
+// left there, so the merges above it do not change.
+pub(crate) fn __hybrid_sort<T, F>(
+    nums: &mut [T],
+    reverse: bool,
+    cmp: &F,
+    parallel: usize,
+    pool: &ThreadPool,
+) where
+    T: Copy + Send + Sync,
+    F: Fn(&T, &T) -> Ordering + Sync,
 {
     let len = nums.len();
     if len <= 1 {
         return;
     }
-    let share_nums = Arc::new(SliceWrapper(nums.as_mut_ptr()));
     if parallel <= 1 {
-        __bitonic_sort(&mut nums[..len / 2], false, parallel);
-        __bitonic_sort(&mut nums[len / 2..], true, parallel);
-    } else {
-        thread::scope(|s| {
-            let nums = share_nums.clone();
-            s.spawn(move || {
-                let nums =
-                    unsafe { slice::from_raw_parts_mut(nums.0, len).get_unchecked_mut(..len / 2) };
-                __bitonic_sort(nums, false, parallel / 2);
-            });
-            let nums = share_nums.clone();
-            s.spawn(move || {
-                let nums =
-                    unsafe { slice::from_raw_parts_mut(nums.0, len).get_unchecked_mut(len / 2..) };
-                __bitonic_sort(nums, true, parallel / 2);
-            });
-        });
-    }
-    let mut size = len;
-    while size > 1 {
-        for i in 0..len / size {
-            __bitonic_merge(&mut nums[i * size..(i + 1) * size], reverse, parallel);
-        }
-        size /= 2;
+        match reverse {
+            false => nums.sort_unstable_by(cmp),
+            true => nums.sort_unstable_by(|a, b| cmp(b, a)),
+        }
+        return;
+    }
+    let (lo, hi) = nums.split_at_mut(len / 2);
+    pool.scope(|s| {
+        s.spawn(|| __hybrid_sort(lo, !reverse, cmp, parallel / 2, pool));
+        s.spawn(|| __hybrid_sort(hi, reverse, cmp, parallel / 2, pool));
+    });
+    __bitonic_merge(nums, reverse, cmp, parallel, 0, pool, None);
+}
+
+// Runs pass `level` of the merges of the subarrays `depth` levels down the recursive
+// sort of `nums`, and returns the number of compare-exchanges done. The subarrays are
+// found by splitting like the sort, with the halves side by side on half the threads
+// each, so no list of them is built.
+fn __sort_pass<T>(
+    nums: &mut [T],
+    reverse: bool,
+    depth: usize,
+    level: usize,
+    parallel: usize,
+    pool: &ThreadPool,
+) -> usize
+where
+    T: PartialOrd + Send,
+{
+    let len = nums.len();
+    if len <= 1 {
+        return 0;
+    }
+    if depth == 0 {
+        return __merge_level(nums, reverse, level, parallel, pool);
+    }
+    let (lo, hi) = nums.split_at_mut(len / 2);
+    __join(
+        pool,
+        parallel,
+        || __sort_pass(lo, !reverse, depth - 1, level, parallel / 2, pool),
+        || __sort_pass(hi, reverse, depth - 1, level, parallel / 2, pool),
+    )
+}
+
+// Runs the half-cleaners of the subarrays `level` levels down the merge of `nums`, and
+// returns the number of compare-exchanges done. A subarray reached with more than one
+// thread left, such as the whole input in the first pass of the last merge, splits its
+// compare-exchanges into chunks like `__bitonic_merge`.
+fn __merge_level<T>(
+    nums: &mut [T],
+    reverse: bool,
+    level: usize,
+    parallel: usize,
+    pool: &ThreadPool,
+) -> usize
+where
+    T: PartialOrd + Send,
+{
+    let len = nums.len();
+    if len <= 1 {
+        return 0;
+    }
+    let stride = bitonic_serial::__stride(len);
+    let (lo, hi) = nums.split_at_mut(stride);
+    if level > 0 {
+        return __join(
+            pool,
+            parallel,
+            || __merge_level(lo, reverse, level - 1, parallel / 2, pool),
+            || __merge_level(hi, reverse, level - 1, parallel / 2, pool),
+        );
+    }
+    let pairs = len - stride;
+    if parallel <= 1 {
+        __half_clean(lo, hi, reverse, &__cmp);
+        return pairs;
     }
+    let size = pairs.div_ceil(parallel);
+    pool.scope(|s| {
+        for (lo, hi) in lo[..pairs].chunks_mut(size).zip(hi.chunks_mut(size)) {
+            s.spawn(move || __half_clean(lo, hi, reverse, &__cmp));
+        }
+    });
+    pairs
+}
+
+// Runs `f` and `g`, side by side on `pool` unless `parallel` is down to a single
+// thread, and returns the sum of their results.
+fn __join<F, G>(pool: &ThreadPool, parallel: usize, f: F, g: G) -> usize
+where
+    F: FnOnce() -> usize + Send,
+    G: FnOnce() -> usize + Send,
+{
+    if parallel <= 1 {
+        return f() + g();
+    }
+    let (mut x, mut y) = (0, 0);
+    pool.scope(|s| {
+        s.spawn(|| x = f());
+        s.spawn(|| y = g());
+    });
+    x + y
+}
+
+// Compare-exchanges `a[i]` with `b[i]` for every `i` of the shorter slice.
+fn __half_clean<T, F>(a: &mut [T], b: &mut [T], reverse: bool, cmp: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    for (num1, num2) in a.iter_mut().zip(b.iter_mut()) {
+        if bitonic_serial::__should_swap(cmp(num1, num2), reverse) {
+            mem::swap(num1, num2);
+        }
+    }
+}
+
+// The number of compare-exchanges of the network for `len` elements. The subarrays of
+// every level of the sort are `short` or `short + 1` long, so each level adds the merges
+// of two lengths, `n_short` and `n_long` times.
+fn __sort_len(len: usize) -> usize {
+    let (mut short, mut n_short, mut n_long) = (len, 1, 0);
+    let mut total = 0;
+    while short > 1 || (short == 1 && n_long > 0) {
+        total += n_short * __merge_len(short) + n_long * __merge_len(short + 1);
+        // an even `short` splits into two halves of `short / 2` and `short + 1` into one
+        // of each; an odd one into one of each and `short + 1` into two longer halves
+        (n_short, n_long) = match short % 2 {
+            0 => (2 * n_short + n_long, n_long),
+            _ => (n_short, n_short + 2 * n_long),
+        };
+        short /= 2;
+    }
+    total
+}
+
+// The number of compare-exchanges of the merge of `len` elements. The part up to the
+// stride is a power of two, whose merge has `log2(stride)` passes of `stride / 2`
+// compare-exchanges; only the rest needs another round.
+fn __merge_len(mut len: usize) -> usize {
+    let mut total = 0;
+    while len > 1 {
+        let stride = bitonic_serial::__stride(len);
+        total += len - stride + stride.ilog2() as usize * stride / 2;
+        len -= stride;
+    }
+    total
+}
+
+// The number of parts to split `len` elements into: `parallel` normalized, capped at
+// the largest power of two not above `len / 2`.
+pub(crate) fn __effective_parallel(parallel: usize, len: usize) -> usize {
+    crate::normalize_parallel(parallel).min(1 << (len / 2).max(1).ilog2())
+}
+
+// Subarrays this short are sorted without polling the cancellation flag.
+const CANCEL_CHECK_LEN: usize = 1024;
+
+// Whether `nums` is handed to the serial network. A cancellable sort keeps recursing on
+// one thread instead, so that it polls the flag before every merge pass.
+fn __serial(len: usize, parallel: usize, min_len: usize, cancel: Option<&AtomicBool>) -> bool {
+    (parallel <= 1 || len < min_len) && (cancel.is_none() || len < CANCEL_CHECK_LEN)
+}
+
+fn __cancelled(cancel: Option<&AtomicBool>) -> bool {
+    cancel.is_some_and(|cancel| cancel.load(atomic::Ordering::Relaxed))
+}
+
+pub(crate) fn __cmp<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{gen_few_unique, gen_tagged, gen_uniform, SEED};
+
+    #[test]
+    fn test_bitonic_sort_hybrid() {
+        for len in [0, 1, 2, 7, 100, 513, 1000] {
+            for parallel in [0, 1, 2, 3, 8, 64] {
+                let mut nums = gen_few_unique(len, 100);
+                let mut expected = nums.clone();
+                expected.sort();
+                bitonic_sort_hybrid(&mut nums, parallel);
+                assert!(
+                    nums == expected,
+                    "unsorted output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+            }
+        }
+    }
 
     #[test]
     fn test_bitonic_sort() {
@@ -200,4 +919,451 @@ mod tests {
         bitonic_sort(&mut nums, parallel);
         assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
     }
+
+    #[test]
+    fn test_bitonic_sort_reverse_wrapper() {
+        use std::cmp::Reverse;
+
+        let mut nums: Vec<Reverse<i32>> = gen_few_unique(1000, 200)
+            .into_iter()
+            .map(|x| Reverse(x as i32 - 100))
+            .collect();
+        let mut expected: Vec<i32> = nums.iter().map(|x| x.0).collect();
+        expected.sort_by(|a, b| b.cmp(a));
+        bitonic_sort(&mut nums, 4);
+        let unwrapped: Vec<i32> = nums.into_iter().map(|x| x.0).collect();
+        assert_eq!(unwrapped, expected);
+
+        let mut nums = vec![Reverse(4), Reverse(2), Reverse(7), Reverse(1), Reverse(5)];
+        bitonic_serial::bitonic_sort(&mut nums);
+        assert_eq!(
+            nums,
+            vec![Reverse(7), Reverse(5), Reverse(4), Reverse(2), Reverse(1)]
+        );
+    }
+
+    #[test]
+    fn test_bitonic_sort_large_input() {
+        for parallel in [1, 2, 200, 255] {
+            let mut nums = gen_few_unique(1024, 1000);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort(&mut nums, parallel);
+            assert!(
+                nums == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_slice() {
+        let mut nums = [4, 2, 7, 1, 5, 3, 6, 4, 2, 7];
+        bitonic_sort_slice(&mut nums[2..], 2);
+        assert_eq!(nums, [4, 2, 1, 2, 3, 4, 5, 6, 7, 7]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_auto() {
+        let mut nums = gen_few_unique(1000, 1000);
+        let mut expected = nums.clone();
+        expected.sort();
+        bitonic_sort_auto(&mut nums);
+        assert_eq!(nums, expected);
+    }
+
+    #[test]
+    fn test_bitonic_sort_with_pool_reused() {
+        let pool = ThreadPool::new(3);
+        for len in [0, 1, 7, 64, 100] {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort_with_pool(&mut nums, 4, &pool);
+            assert_eq!(nums, expected);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_with_min_len() {
+        for min_parallel_len in [0, 1, 64, 4096] {
+            let mut nums = gen_few_unique(1000, 1000);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort_with_min_len(&mut nums, 8, min_parallel_len);
+            assert!(
+                nums == expected,
+                "unsorted output for min_parallel_len = {}",
+                min_parallel_len
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sorted() {
+        let nums = [4, 2, 7, 1, 5, 3, 6];
+        assert_eq!(bitonic_sorted(&nums, 2), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(nums, [4, 2, 7, 1, 5, 3, 6]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_every_length() {
+        for len in 0..100 {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort(&mut nums, 4);
+            assert!(nums == expected, "unsorted output for len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_keeps_capacity() {
+        let mut nums: Vec<u32> = Vec::with_capacity(1000);
+        nums.extend(gen_few_unique(1000, 1000));
+        bitonic_sort(&mut nums, 4);
+        assert_eq!(nums.capacity(), 1000);
+    }
+
+    #[test]
+    fn test_bitonic_sort_deterministic_across_parallel() {
+        // 0.0 and -0.0 compare equal but differ in their bits, NaN compares with nothing
+        let nums: Vec<f64> = gen_few_unique(5000, 7)
+            .into_iter()
+            .map(|x| match x {
+                0 => 0.0,
+                1 => -0.0,
+                2 => f64::NAN,
+                n => n as f64,
+            })
+            .collect();
+        let mut expected = nums.clone();
+        bitonic_sort_with_min_len(&mut expected, 1, 0);
+        let expected: Vec<u64> = expected.iter().map(|x| x.to_bits()).collect();
+        for parallel in [2, 4, 8] {
+            for min_len in [0, 64, DEFAULT_MIN_PARALLEL_LEN] {
+                let mut sorted = nums.clone();
+                bitonic_sort_with_min_len(&mut sorted, parallel, min_len);
+                let sorted: Vec<u64> = sorted.iter().map(|x| x.to_bits()).collect();
+                assert!(
+                    sorted == expected,
+                    "output differs for parallel = {}, min_len = {}",
+                    parallel,
+                    min_len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_cancellable() {
+        let nums = gen_few_unique(5000, 1000);
+        let mut expected = nums.clone();
+        expected.sort();
+        for parallel in [1, 4] {
+            let mut sorted = nums.clone();
+            let cancel = AtomicBool::new(false);
+            assert_eq!(
+                bitonic_sort_cancellable(&mut sorted, parallel, &cancel),
+                Ok(())
+            );
+            assert!(
+                sorted == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+
+            let mut cancelled = nums.clone();
+            cancel.store(true, atomic::Ordering::Relaxed);
+            assert_eq!(
+                bitonic_sort_cancellable(&mut cancelled, parallel, &cancel),
+                Err(SortError::Cancelled)
+            );
+            assert!(cancelled == nums, "cancelled sort moved elements");
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_with_progress() {
+        for len in [0, 1, 2, 7, 64, 1000] {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            let mut reports = Vec::new();
+            bitonic_sort_with_progress(&mut nums, 4, |done| reports.push(done));
+            assert!(nums == expected, "unsorted output for len = {}", len);
+            assert!(reports.windows(2).all(|w| w[0] < w[1]));
+            assert_eq!(reports.last().copied(), (len > 1).then_some(1.0));
+        }
+    }
+
+    #[test]
+    fn test_sort_len_matches_network() {
+        for len in (0..100).chain([127, 128, 129, 1000, 4097]) {
+            assert_eq!(__sort_len(len), bitonic_serial::bitonic_network(len).len());
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_ord() {
+        for parallel in [1, 2, 8] {
+            let mut nums: Vec<(u32, char)> = gen_few_unique(1000, 100)
+                .into_iter()
+                .zip(['a', 'b'].into_iter().cycle())
+                .collect();
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort_ord(&mut nums, parallel);
+            assert!(
+                nums == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_parallel_values() {
+        for parallel in [0, 1, 3, 255, 300] {
+            let mut nums = gen_few_unique(1000, 1000);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort(&mut nums, parallel);
+            assert!(
+                nums == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_desc() {
+        for len in [7, 1000, 4097] {
+            let nums = gen_few_unique(len, 100);
+            let mut expected = nums.clone();
+            bitonic_serial::bitonic_sort_desc(&mut expected);
+            let mut sorted = nums.clone();
+            bitonic_sort_desc(&mut sorted, 4);
+            assert!(sorted == expected, "wrong output for len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_range() {
+        let nums: Vec<f64> = gen_few_unique(1000, 1000)
+            .into_iter()
+            .map(|x| x as f64 - 500.0)
+            .collect();
+        let mut sorted = nums.clone();
+        bitonic_sort_range(&mut sorted, 100..803, 4);
+        let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&sorted[..100]), bits(&nums[..100]));
+        assert_eq!(bits(&sorted[803..]), bits(&nums[803..]));
+        let mut expected = nums[100..803].to_vec();
+        expected.sort_by(f64::total_cmp);
+        assert_eq!(sorted[100..803], expected[..]);
+    }
+
+    #[test]
+    fn test_effective_parallel() {
+        assert_eq!(__effective_parallel(8, 0), 1);
+        assert_eq!(__effective_parallel(8, 2), 1);
+        assert_eq!(__effective_parallel(8, 7), 2);
+        assert_eq!(__effective_parallel(8, 16), 8);
+        assert_eq!(__effective_parallel(3, 1000), 4);
+        // the halving recursion needs a power of two at every level, whatever is asked for
+        assert_eq!(__effective_parallel(200, 1000), 256);
+        assert_eq!(__effective_parallel(255, 1000), 256);
+        assert_eq!(__effective_parallel(200, 300), 128);
+        for parallel in [200, 255, 257, usize::MAX] {
+            for len in [0, 1, 5, 300, 1000, usize::MAX] {
+                assert!(__effective_parallel(parallel, len).is_power_of_two());
+            }
+        }
+
+        let mut nums = vec![2, 1];
+        bitonic_sort(&mut nums, 8);
+        assert_eq!(nums, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_arrays() {
+        let mut rows: Vec<[u16; 3]> = gen_uniform(1000, SEED)
+            .into_iter()
+            .zip(0..)
+            .map(|(h, x)| [(h % 4) as u16, ((h >> 8) % 4) as u16, x])
+            .collect();
+        let mut expected = rows.clone();
+        expected.sort();
+        bitonic_sort(&mut rows, 4);
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn test_bitonic_sort_adaptive() {
+        let mut nums: Vec<u32> = (0..1000).map(|x| x * 2).collect();
+        bitonic_sort_adaptive(&mut nums, 4);
+        assert_eq!(nums, (0..1000).map(|x| x * 2).collect::<Vec<_>>());
+
+        for tail in [1, 10, 333, 1000] {
+            let mut nums: Vec<u32> = (0..1000).map(|x| x * 2).collect();
+            nums.extend(gen_few_unique(tail, 2500));
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort_adaptive(&mut nums, 4);
+            assert!(nums == expected, "unsorted output for tail = {}", tail);
+        }
+
+        let mut nums: Vec<u32> = vec![];
+        bitonic_sort_adaptive(&mut nums, 4);
+        assert!(nums.is_empty());
+    }
+
+    #[test]
+    fn test_bitonic_sort_adaptive_constant() {
+        use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+        static COMPARISONS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Counted(u32);
+
+        impl PartialOrd for Counted {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                COMPARISONS.fetch_add(1, AtomicOrdering::Relaxed);
+                self.0.partial_cmp(&other.0)
+            }
+        }
+
+        let len = 1_000_000;
+        let mut nums = vec![Counted(7); len];
+        bitonic_sort_adaptive(&mut nums, 4);
+        assert!(nums.iter().all(|&x| x == Counted(7)));
+        assert_eq!(COMPARISONS.load(AtomicOrdering::Relaxed), len - 1);
+    }
+
+    #[test]
+    fn test_into_sorted_vec() {
+        for len in [0, 1, 7, 100, 1000] {
+            let expected: Vec<u32> = {
+                let mut nums = gen_few_unique(len, 500);
+                nums.sort();
+                nums
+            };
+            let iter = gen_few_unique(len, 500).into_iter();
+            assert!(
+                into_sorted_vec(iter, 4) == expected,
+                "unsorted output for len = {}",
+                len
+            );
+        }
+        let set = std::collections::HashSet::from([3, 1, 2]);
+        assert_eq!(into_sorted_vec(set, 2), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sort_is_stable() {
+        for parallel in [1, 4, 16] {
+            let mut nums = gen_tagged(1000, 10);
+            let mut expected = nums.clone();
+            expected.sort_by_key(|record| record.0);
+            sort(&mut nums, parallel);
+            assert!(
+                nums == expected,
+                "unstable output for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_dyn() {
+        let cmp: &(dyn Fn(&u32, &u32) -> Ordering + Sync) = &|a, b| b.cmp(a);
+        for parallel in [1, 4, 16] {
+            let mut nums = gen_few_unique(1000, 1000);
+            let mut expected = nums.clone();
+            expected.sort_by(cmp);
+            bitonic_sort_dyn(&mut nums, parallel, cmp);
+            assert!(nums == expected, "parallel {}", parallel);
+        }
+    }
+
+    #[test]
+    fn test_threads_bounded_by_parallel() {
+        use std::collections::HashSet;
+        use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+        use std::sync::Mutex;
+        use std::thread;
+
+        for (parallel, limit) in [(1, 1), (2, 2), (3, 4), (8, 8), (16, 16)] {
+            let threads = Mutex::new(HashSet::new());
+            let active = AtomicUsize::new(0);
+            let peak = AtomicUsize::new(0);
+            let cmp = |a: &u32, b: &u32| {
+                let now = active.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+                peak.fetch_max(now, AtomicOrdering::SeqCst);
+                threads.lock().unwrap().insert(thread::current().id());
+                active.fetch_sub(1, AtomicOrdering::SeqCst);
+                a.cmp(b)
+            };
+            let mut nums = gen_uniform(4000, SEED);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort_dyn(&mut nums, parallel, &cmp);
+            assert!(
+                nums == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+            let threads = threads.into_inner().unwrap().len();
+            assert!(
+                threads <= limit,
+                "{} threads for parallel = {}",
+                threads,
+                parallel
+            );
+            let peak = peak.into_inner();
+            assert!(
+                peak <= limit,
+                "{} at once for parallel = {}",
+                peak,
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_sort_pass_splits_like_serial() {
+        // passes with a thread per subarray, and with subarrays split into chunks for
+        // more threads than subarrays, must leave the same result as a single thread
+        let pool = ThreadPool::new(3);
+        for len in [2usize, 3, 5, 8, 100, 1000] {
+            let nums = gen_few_unique(len, 50);
+            let levels = usize::BITS - (len - 1).leading_zeros();
+            for depth in 0..levels as usize {
+                for level in 0..levels as usize {
+                    let mut expected = nums.clone();
+                    let done = __sort_pass(&mut expected, false, depth, level, 1, &pool);
+                    for parallel in [2, 4, 16] {
+                        let mut split = nums.clone();
+                        assert_eq!(
+                            __sort_pass(&mut split, false, depth, level, parallel, &pool),
+                            done
+                        );
+                        assert!(
+                            split == expected,
+                            "different output for len = {}, depth = {}, level = {}, \
+                             parallel = {}",
+                            len,
+                            depth,
+                            level,
+                            parallel
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,158 @@
+//! This module provides the parallel bitonic sort on top of rayon, behind the `rayon`
+//! feature.
+//!
+//! The recursive split runs through `rayon::join` and the comparator passes of every
+//! merge through `par_chunks_mut`, so the halves are handed to other threads as plain
+//! `&mut [T]` and no raw pointers are involved. The work runs on the rayon pool the
+//! caller is in, so `ThreadPool::install` controls which threads sort.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::bitonic_rayon::bitonic_sort;
+//!
+//! let mut nums = vec![4, 2, 7, 1, 5, 3, 6];
+//! bitonic_sort(&mut nums);
+//! assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+//! ```
+
+// the `Vec` entry points mirror `bitonic_parallel`
+#![allow(clippy::ptr_arg)]
+
+use crate::bitonic_parallel::DEFAULT_MIN_PARALLEL_LEN;
+use crate::bitonic_serial;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::mem;
+
+// The number of compare-exchanges a single rayon task performs in a merge pass.
+const PASS_CHUNK_LEN: usize = 1024;
+
+/// Sorts the given vector in ascending order on the current rayon pool.
+///
+/// Subarrays shorter than [`DEFAULT_MIN_PARALLEL_LEN`] are sorted serially.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_rayon::bitonic_sort;
+///
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// pool.install(|| bitonic_sort(&mut nums));
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd + Send,
+{
+    bitonic_sort_by(nums, |a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+}
+
+/// Sorts the given vector with a custom comparator closure on the current rayon pool.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_rayon::bitonic_sort_by;
+///
+/// let mut nums = vec![(1, 'a'), (3, 'b'), (2, 'c')];
+/// bitonic_sort_by(&mut nums, |a, b| b.0.cmp(&a.0));
+/// assert_eq!(nums, vec![(3, 'b'), (2, 'c'), (1, 'a')]);
+/// ```
+pub fn bitonic_sort_by<T, F>(nums: &mut Vec<T>, cmp: F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    __bitonic_sort(&mut nums[..], false, &cmp);
+}
+
+fn __bitonic_merge<T, F>(nums: &mut [T], reverse: bool, cmp: &F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let len = nums.len();
+    if len < DEFAULT_MIN_PARALLEL_LEN {
+        bitonic_serial::__bitonic_merge(nums, reverse, cmp);
+        return;
+    }
+    let stride = bitonic_serial::__stride(len);
+    let (lo, hi) = nums.split_at_mut(stride);
+    lo[..hi.len()]
+        .par_chunks_mut(PASS_CHUNK_LEN)
+        .zip(hi.par_chunks_mut(PASS_CHUNK_LEN))
+        .for_each(|(lo, hi)| {
+            for (num1, num2) in lo.iter_mut().zip(hi.iter_mut()) {
+                if bitonic_serial::__should_swap(cmp(num1, num2), reverse) {
+                    mem::swap(num1, num2);
+                }
+            }
+        });
+    rayon::join(
+        || __bitonic_merge(lo, reverse, cmp),
+        || __bitonic_merge(hi, reverse, cmp),
+    );
+}
+
+fn __bitonic_sort<T, F>(nums: &mut [T], reverse: bool, cmp: &F)
+where
+    T: Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let len = nums.len();
+    if len < DEFAULT_MIN_PARALLEL_LEN {
+        bitonic_serial::__bitonic_sort(nums, reverse, cmp);
+        return;
+    }
+    let (lo, hi) = nums.split_at_mut(len / 2);
+    rayon::join(
+        || __bitonic_sort(lo, !reverse, cmp),
+        || __bitonic_sort(hi, reverse, cmp),
+    );
+    __bitonic_merge(nums, reverse, cmp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_bitonic_sort() {
+        let mut nums = vec![4, 2, 7, 1, 5, 3, 6];
+        bitonic_sort(&mut nums);
+        assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_large_input() {
+        for len in [4095, 4096, 10_000, 65_537] {
+            let mut nums = gen_few_unique(len, 1000);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort(&mut nums);
+            assert!(nums == expected, "unsorted output for len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_matches_std_threads() {
+        let nums: Vec<f64> = gen_few_unique(10_000, 7)
+            .into_iter()
+            .map(|x| match x {
+                0 => 0.0,
+                1 => -0.0,
+                2 => f64::NAN,
+                n => n as f64,
+            })
+            .collect();
+        let mut expected = nums.clone();
+        crate::bitonic_parallel::bitonic_sort(&mut expected, 4);
+        let mut sorted = nums;
+        bitonic_sort(&mut sorted);
+        let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&sorted), bits(&expected));
+    }
+}
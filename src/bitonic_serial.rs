@@ -13,77 +13,1172 @@
 //! bitonic_sort(&mut nums);
 //! assert_eq!(nums, vec![1, 2, 4, 5, 7]);
 //! ```
+//!
+//! # Stability
+//!
+//! Like the standard library, [`sort`] is stable and [`sort_unstable`] is not. The
+//! network of `sort_unstable` may reorder equal elements and sorts in place without
+//! allocating. `sort` keeps equal elements in their input order by sorting each element
+//! together with its input index, which takes a buffer of `n` such pairs.
+//!
+//! The sorts by key are stable the same way, as they sort each key together with its
+//! input index: [`bitonic_sort_by_key`], [`bitonic_sort_by_cached_key`],
+//! [`bitonic_sort_by_key_into`] and [`bitonic_sort_pairs`]. Every other sort of this
+//! module is unstable.
+
+// the `Vec` entry points mirror those of the parallel sorts even though this network
+// handles every length in place
+#![allow(clippy::ptr_arg)]
+
+use crate::validate;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::{Cell, RefCell};
+use core::cmp::Ordering;
+use core::mem;
+
 /// Performs a bitonic sort on the given mutable slice of elements.
 ///
-/// The `bitonic_sort` function sorts the elements in ascending order by default.
-/// If the `reverse` parameter is set to `true`, it sorts the elements in descending order.
+/// The `bitonic_sort` function sorts the elements in ascending order.
+/// Use [`bitonic_sort_desc`] to sort the elements in descending order.
+///
+/// The vector is sorted in place: the network handles every length without padding, so
+/// neither its length nor its capacity changes. The same holds for the other `Vec`
+/// functions of this module.
+///
+/// Up to eight elements are sorted with fixed size-optimal networks instead of the
+/// bitonic one, which needs 24 comparators for eight elements where 19 suffice.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort(&mut nums);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd,
+{
+    let cmp = |a: &T, b: &T| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+    if nums.len() < SMALL_NETWORKS.len() {
+        __small_sort(nums, &cmp);
+        return;
+    }
+    bitonic_sort_by(nums, cmp);
+}
+
+/// Sorts the given vector in ascending order, keeping equal elements in their input
+/// order.
+///
+/// Each element is moved into a buffer together with its input index, and ties are
+/// broken by the index, so no two elements ever compare equal in the network. The buffer
+/// holds `n` `(T, usize)` pairs. The guarantee covers elements that compare as equal;
+/// incomparable ones such as `NaN` have no defined place.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::sort;
+/// use std::cmp::Ordering;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Task {
+///     priority: u8,
+///     name: &'static str,
+/// }
+///
+/// impl PartialOrd for Task {
+///     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+///         self.priority.partial_cmp(&other.priority)
+///     }
+/// }
+///
+/// let mut tasks = vec![
+///     Task { priority: 2, name: "deploy" },
+///     Task { priority: 1, name: "test" },
+///     Task { priority: 2, name: "announce" },
+///     Task { priority: 1, name: "build" },
+/// ];
+/// sort(&mut tasks);
+/// let names: Vec<&str> = tasks.iter().map(|task| task.name).collect();
+/// assert_eq!(names, vec!["test", "build", "deploy", "announce"]);
+/// ```
+pub fn sort<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd,
+{
+    let mut pairs: Vec<(T, usize)> = nums.drain(..).zip(0..).collect();
+    __bitonic_sort(&mut pairs[..], false, &|a: &(T, usize), b: &(T, usize)| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(Ordering::Equal)
+            .then(a.1.cmp(&b.1))
+    });
+    nums.extend(pairs.into_iter().map(|(num, _)| num));
+}
+
+/// Sorts the given vector in ascending order without keeping equal elements in their
+/// input order.
+///
+/// This is [`bitonic_sort`] under the standard library's name: it sorts in place and
+/// never allocates.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::sort_unstable;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// sort_unstable(&mut nums);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn sort_unstable<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd,
+{
+    bitonic_sort(nums);
+}
+
+/// Sorts the given vector with a custom comparator closure.
+///
+/// The comparator decides every compare-exchange of the network: two elements are
+/// swapped when `cmp` returns `Ordering::Greater` for them.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by;
+///
+/// let mut nums = vec![(1, 'a'), (3, 'b'), (2, 'c')];
+/// bitonic_sort_by(&mut nums, |a, b| b.0.cmp(&a.0));
+/// assert_eq!(nums, vec![(3, 'b'), (2, 'c'), (1, 'a')]);
+/// ```
+pub fn bitonic_sort_by<T, F>(nums: &mut Vec<T>, cmp: F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    __bitonic_sort(&mut nums[..], false, &cmp);
+}
+
+/// Sorts the given vector like [`bitonic_sort_by`], first checking in debug builds that
+/// `cmp` is a total order.
+///
+/// An inconsistent comparator does not make the network fail, it just leaves the output
+/// in some order that is not sorted. The check samples a few hundred triples of
+/// elements and panics, naming their positions, if `cmp` is not antisymmetric or not
+/// transitive on them, or finds an element unequal to itself. Release builds skip it,
+/// and a comparator can still be wrong on elements that were not sampled.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by_checked;
+///
+/// let mut nums = vec![(1, 'a'), (3, 'b'), (2, 'c')];
+/// bitonic_sort_by_checked(&mut nums, |a, b| b.0.cmp(&a.0));
+/// assert_eq!(nums, vec![(3, 'b'), (2, 'c'), (1, 'a')]);
+/// ```
+///
+/// ```should_panic
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by_checked;
+/// use std::cmp::Ordering;
+///
+/// // never says `Greater`, so it contradicts itself on every pair of distinct elements
+/// let mut nums = vec![4, 2, 7, 1];
+/// bitonic_sort_by_checked(&mut nums, |a, b| match a == b {
+///     true => Ordering::Equal,
+///     false => Ordering::Less,
+/// });
+/// # #[cfg(not(debug_assertions))]
+/// # panic!("the check only runs in debug builds");
+/// ```
+pub fn bitonic_sort_by_checked<T, F>(nums: &mut Vec<T>, cmp: F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    validate::__debug_assert_total_order(nums, &cmp);
+    __bitonic_sort(&mut nums[..], false, &cmp);
+}
+
+/// Sorts the given vector with a comparator closure that may mutate its state.
+///
+/// The comparator can count its calls or fill a cache as it goes. This only exists in
+/// the serial module: its network runs one comparison after the other, while the
+/// parallel sorts call the comparator from several threads at once, which needs `Fn`
+/// and `Sync`. The results are those of [`bitonic_sort_by`].
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by_mut;
+///
+/// let mut comparisons = 0;
+/// let mut nums = vec![4, 2, 7, 1];
+/// bitonic_sort_by_mut(&mut nums, |a, b| {
+///     comparisons += 1;
+///     a.cmp(b)
+/// });
+/// assert_eq!(nums, vec![1, 2, 4, 7]);
+/// assert_eq!(comparisons, 6);
+/// ```
+pub fn bitonic_sort_by_mut<T, F>(nums: &mut Vec<T>, cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // the network never calls the comparator from within itself, so the borrow is free
+    let cmp = RefCell::new(cmp);
+    __bitonic_sort(&mut nums[..], false, &|a: &T, b: &T| {
+        (cmp.borrow_mut())(a, b)
+    });
+}
+
+/// Sorts the given vector with a comparator for a partial order.
+///
+/// A `None` from `cmp` means the two elements are incomparable, and the network leaves
+/// such a pair where it is, exactly like an `Ordering::Equal`; nothing panics. Chains of
+/// mutually comparable elements come out in order and elements that are comparable to
+/// nothing keep their position. In between, the result is only topological-ish: the
+/// network relies on transitivity that a partial order does not provide, so an element
+/// may still end up after one it is less than when incomparable elements sit between
+/// them. Use a total order, or [`bitonic_sort_by_key_into`] with one, when that matters.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_partial;
+///
+/// // divisibility: `a` comes before `b` if it divides it
+/// let divides = |a: &u32, b: &u32| match (b.is_multiple_of(*a), a.is_multiple_of(*b)) {
+///     (true, true) => Some(std::cmp::Ordering::Equal),
+///     (true, false) => Some(std::cmp::Ordering::Less),
+///     (false, true) => Some(std::cmp::Ordering::Greater),
+///     (false, false) => None,
+/// };
+/// let mut nums = vec![8, 2, 16, 1, 4];
+/// bitonic_sort_partial(&mut nums, divides);
+/// assert_eq!(nums, vec![1, 2, 4, 8, 16]);
+///
+/// let mut primes = vec![7, 3, 5, 2];
+/// bitonic_sort_partial(&mut primes, divides);
+/// assert_eq!(primes, vec![7, 3, 5, 2]);
+/// ```
+pub fn bitonic_sort_partial<T, F>(nums: &mut Vec<T>, cmp: F)
+where
+    F: Fn(&T, &T) -> Option<Ordering>,
+{
+    __bitonic_sort(&mut nums[..], false, &|a: &T, b: &T| {
+        cmp(a, b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the given vector with a comparator chosen at runtime.
+///
+/// This is [`bitonic_sort_by`] for a trait object, such as a comparator picked from a
+/// table or handed over by a plugin. Every comparison is a virtual call, but the sort
+/// is compiled once for `T` instead of once per comparator type.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_dyn;
+/// use std::cmp::Ordering;
+///
+/// let descending = true;
+/// let cmp: Box<dyn Fn(&i32, &i32) -> Ordering> = if descending {
+///     Box::new(|a, b| b.cmp(a))
+/// } else {
+///     Box::new(|a, b| a.cmp(b))
+/// };
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort_dyn(&mut nums, &*cmp);
+/// assert_eq!(nums, vec![7, 5, 4, 2, 1]);
+/// ```
+pub fn bitonic_sort_dyn<T>(nums: &mut Vec<T>, cmp: &dyn Fn(&T, &T) -> Ordering) {
+    __bitonic_sort(&mut nums[..], false, &cmp);
+}
+
+/// Sorts the given vector of totally ordered elements.
+///
+/// This compares with `Ord::cmp` directly, so unlike [`bitonic_sort`] no pair of
+/// elements can be incomparable. Use it for integers, strings and other `Ord` types;
+/// the `PartialOrd` functions remain for floats.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_ord;
+///
+/// let mut words = vec![String::from("pear"), String::from("apple"), String::from("fig")];
+/// bitonic_sort_ord(&mut words);
+/// assert_eq!(words, vec!["apple", "fig", "pear"]);
+/// ```
+pub fn bitonic_sort_ord<T>(nums: &mut Vec<T>)
+where
+    T: Ord,
+{
+    __bitonic_sort(&mut nums[..], false, &T::cmp);
+    validate::__debug_assert_sorted(nums, false, &T::cmp);
+}
+
+/// Sorts fixed-size arrays lexicographically, like the rows of a table by its columns.
+///
+/// The first element decides, and each following element breaks the ties left by the
+/// ones before it. This is the `PartialOrd` of `[T; N]`, so [`bitonic_sort`] gives the
+/// same result; this function only names the intent and pins the element type.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_lex;
+///
+/// let mut rows = vec![[2, 1, 9], [1, 5, 0], [2, 0, 3], [1, 5, -1]];
+/// bitonic_sort_lex(&mut rows);
+/// assert_eq!(rows, vec![[1, 5, -1], [1, 5, 0], [2, 0, 3], [2, 1, 9]]);
+/// ```
+pub fn bitonic_sort_lex<T, const N: usize>(nums: &mut Vec<[T; N]>)
+where
+    T: PartialOrd,
+{
+    bitonic_sort(nums);
+}
+
+/// Sorts the given slice by a key that is computed only once per element.
+///
+/// The keys are collected into a side buffer of `(key, index)` pairs, which is sorted
+/// instead of the elements; the resulting permutation is then applied to `nums` in place
+/// by following its cycles. This avoids recomputing an expensive key on every one of the
+/// O(n log^2 n) comparisons. The side buffer is the only extra allocation, using about
+/// `n * size_of::<K>() + n * size_of::<usize>()` bytes. Elements with equal keys keep
+/// their input order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by_key;
+///
+/// let mut nums = vec!["2024-03-01", "2023-12-24", "2024-01-15"];
+/// bitonic_sort_by_key(&mut nums, |date| date.replace('-', "").parse::<u32>().unwrap());
+/// assert_eq!(nums, vec!["2023-12-24", "2024-01-15", "2024-03-01"]);
+/// ```
+pub fn bitonic_sort_by_key<T, K, F>(nums: &mut [T], key: F)
+where
+    K: PartialOrd,
+    F: Fn(&T) -> K,
+{
+    let keys: Vec<(K, usize)> = nums.iter().map(key).zip(0..).collect();
+    __sort_by_keys(nums, keys, &|a: &(K, usize), b: &(K, usize)| {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the given slice by a key computed once per element, like the standard
+/// library's `sort_by_cached_key`.
+///
+/// Neither the elements nor the keys need to be `Copy`: the keys, which may be `String`s
+/// or anything else expensive to compute, are sorted together with their input indices,
+/// and the elements are then moved into place by swapping along the cycles of the
+/// resulting permutation. This takes a buffer of `n` `(K, usize)` pairs. Elements with
+/// equal keys keep their input order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by_cached_key;
+///
+/// let mut paths: Vec<String> = ["b/c.txt", "a.txt", "c/d/e.txt"].map(String::from).into();
+/// // sort by file name, which is only computed once per path
+/// bitonic_sort_by_cached_key(&mut paths, |path| path.rsplit('/').next().map(String::from));
+/// assert_eq!(paths, vec!["a.txt", "b/c.txt", "c/d/e.txt"]);
+/// ```
+pub fn bitonic_sort_by_cached_key<T, K, F>(nums: &mut [T], key: F)
+where
+    K: PartialOrd,
+    F: FnMut(&T) -> K,
+{
+    let keys: Vec<(K, usize)> = nums.iter().map(key).zip(0..).collect();
+    __sort_by_keys(nums, keys, &|a: &(K, usize), b: &(K, usize)| {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts `(key, value)` pairs by their key only, keeping pairs with equal keys in their
+/// input order.
+///
+/// The other sorts compare tuples with their `PartialOrd`, which is lexicographic: on
+/// equal keys the values decide. Here the values are never compared, so they need no
+/// order at all, and are moved rather than copied. This is [`bitonic_sort_by_key`] with
+/// a clone of the first field as the key.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_pairs;
+///
+/// let mut scores = vec![(2, "carol"), (1, "dave"), (2, "alice"), (1, "bob")];
+/// bitonic_sort_pairs(&mut scores);
+/// assert_eq!(scores, vec![(1, "dave"), (1, "bob"), (2, "carol"), (2, "alice")]);
+/// ```
+pub fn bitonic_sort_pairs<K, V>(nums: &mut [(K, V)])
+where
+    K: PartialOrd + Clone,
+{
+    bitonic_sort_by_key(nums, |pair| pair.0.clone());
+}
+
+/// Sorts a vector of references by the values they point to.
+///
+/// Only the references move, so large elements are sorted without being copied or
+/// cloned, and the referenced values stay where they are. `&T` is `Copy` for any `T`, so
+/// the `Copy`-bound sorts accept such a vector as well, the parallel ones included.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_refs;
+///
+/// let words = vec![String::from("pear"), String::from("apple"), String::from("fig")];
+/// let mut refs: Vec<&String> = words.iter().collect();
+/// bitonic_sort_refs(&mut refs);
+/// assert_eq!(refs, vec!["apple", "fig", "pear"]);
+/// ```
+pub fn bitonic_sort_refs<T>(nums: &mut Vec<&T>)
+where
+    T: PartialOrd + ?Sized,
+{
+    bitonic_sort_by(nums, |a, b| {
+        (**a).partial_cmp(*b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the given slice by a totally ordered key, computed once per element.
+///
+/// This works like [`bitonic_sort_by_key`], but the key type only has to be `Ord`, so
+/// `T` itself may be partially ordered: projecting each element into a total order
+/// sorts types like `f64` without any incomparable pairs. [`f64_total_key`] is such a
+/// projection for `f64`, under which every `NaN` has a well-defined place.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::{bitonic_sort_by_key_into, f64_total_key};
+///
+/// let mut nums = vec![2.5, f64::NAN, -1.0, 0.0, -0.0];
+/// bitonic_sort_by_key_into(&mut nums, f64_total_key);
+/// assert_eq!(nums[..4], [-1.0, -0.0, 0.0, 2.5]);
+/// assert!(nums[4].is_nan());
+/// ```
+pub fn bitonic_sort_by_key_into<T, K, F>(nums: &mut [T], key: F)
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+{
+    let keys: Vec<(K, usize)> = nums.iter().map(key).zip(0..).collect();
+    __sort_by_keys(nums, keys, &|a: &(K, usize), b: &(K, usize)| a.cmp(b));
+}
+
+/// Sorts the given slice by a key borrowed from each element, compared as a `Q`.
+///
+/// `key` returns a reference into the element, so unlike [`bitonic_sort_by_key`] no key
+/// is ever owned or allocated; it is simply looked up again for every comparison. The
+/// keys are compared through `K: Borrow<Q>`, which lets a `K` without an order of its own
+/// be sorted by the order of what it borrows as, such as a `String` by its `str`. `Q`
+/// cannot be inferred from `key` and is usually named with a turbofish.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_by_borrowed_key;
+///
+/// let mut users = vec![(3, String::from("carol")), (1, String::from("alice"))];
+/// bitonic_sort_by_borrowed_key::<_, String, str, _>(&mut users, |user| &user.1);
+/// assert_eq!(users[0].1, "alice");
+/// ```
+pub fn bitonic_sort_by_borrowed_key<T, K, Q, F>(nums: &mut [T], key: F)
+where
+    F: Fn(&T) -> &K,
+    K: Borrow<Q> + ?Sized,
+    Q: PartialOrd + ?Sized,
+{
+    __bitonic_sort(nums, false, &|a: &T, b: &T| {
+        key(a)
+            .borrow()
+            .partial_cmp(key(b).borrow())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Maps an `f64` to an `i64` whose order is the IEEE 754 total order of the float.
+///
+/// Negative `NaN`s sort first and positive `NaN`s last, `-0.0` sorts before `0.0`, and
+/// every other value keeps its numeric order; this is the order of [`f64::total_cmp`].
+/// Pass it to [`bitonic_sort_by_key_into`] to sort floats that may contain `NaN`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::f64_total_key;
+///
+/// assert!(f64_total_key(&-1.0) < f64_total_key(&-0.0));
+/// assert!(f64_total_key(&-0.0) < f64_total_key(&0.0));
+/// assert!(f64_total_key(&f64::INFINITY) < f64_total_key(&f64::NAN));
+/// ```
+pub fn f64_total_key(x: &f64) -> i64 {
+    let bits = x.to_bits() as i64;
+    // negative floats order backwards as integers, so flip all bits but the sign
+    bits ^ (((bits >> 63) as u64) >> 1) as i64
+}
+
+// Sorts the `(key, index)` buffer, then applies the permutation to `nums` in place.
+fn __sort_by_keys<T, K, F>(nums: &mut [T], mut keys: Vec<(K, usize)>, cmp: &F)
+where
+    F: Fn(&(K, usize), &(K, usize)) -> Ordering,
+{
+    __bitonic_sort(&mut keys[..], false, cmp);
+    __permute(nums, &mut keys);
+}
+
+// Moves the element with input index `keys[i].1` to `i` for every `i`, with swaps along
+// the cycles of the permutation. The indices are overwritten on the way.
+pub(crate) fn __permute<T, K>(nums: &mut [T], keys: &mut [(K, usize)]) {
+    // walk each cycle with swaps and mark visited slots by pointing them at themselves
+    for start in 0..keys.len() {
+        let mut current = start;
+        loop {
+            let next = keys[current].1;
+            keys[current].1 = current;
+            if next == start {
+                break;
+            }
+            nums.swap(current, next);
+            current = next;
+        }
+    }
+}
+
+/// Returns a sorted copy of `nums`, leaving the input untouched.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sorted;
+///
+/// let nums = [4, 2, 7, 1, 5];
+/// assert_eq!(bitonic_sorted(&nums), vec![1, 2, 4, 5, 7]);
+/// assert_eq!(nums, [4, 2, 7, 1, 5]);
+/// ```
+pub fn bitonic_sorted<T>(nums: &[T]) -> Vec<T>
+where
+    T: PartialOrd + Clone,
+{
+    let mut sorted = nums.to_vec();
+    bitonic_sort(&mut sorted);
+    sorted
+}
+
+/// Sorts `nums` and returns it as a boxed slice without excess capacity.
+///
+/// `into_boxed_slice` shrinks the allocation to the length, which releases any spare
+/// capacity the vector was built with. Use it for sorted results that are kept around
+/// for a long time.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_boxed;
+///
+/// let mut nums = Vec::with_capacity(64);
+/// nums.extend([4, 2, 7, 1, 5]);
+/// let sorted = bitonic_sort_boxed(nums);
+/// assert_eq!(*sorted, [1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_boxed<T>(mut nums: Vec<T>) -> Box<[T]>
+where
+    T: PartialOrd,
+{
+    bitonic_sort(&mut nums);
+    nums.into_boxed_slice()
+}
+
+/// Sorts the given vector in descending order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_desc;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// bitonic_sort_desc(&mut nums);
+/// assert_eq!(nums, vec![7, 5, 4, 2, 1]);
+/// ```
+pub fn bitonic_sort_desc<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd,
+{
+    __bitonic_sort(&mut nums[..], true, &|a: &T, b: &T| {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the given slice in ascending order, for callers that do not own a `Vec`.
+///
+/// The network handles every length directly, so the slice is sorted in place
+/// without allocating.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_slice;
+///
+/// let mut nums = [4, 2, 7, 1, 5];
+/// bitonic_sort_slice(&mut nums);
+/// assert_eq!(nums, [1, 2, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_slice<T>(nums: &mut [T])
+where
+    T: PartialOrd,
+{
+    __bitonic_sort(nums, false, &|a: &T, b: &T| {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the given array in ascending order, in place and without allocating.
+///
+/// The network needs no padding for lengths that are not a power of two and runs as
+/// loops rather than recursion, so the stack holds a few indices whatever `N` is, and
+/// no bound on `N` is needed. Up to eight elements take the fixed networks of
+/// [`bitonic_sort`]. This is available without the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_array;
+///
+/// let mut nums = [4, 2, 7, 1, 5, 3];
+/// bitonic_sort_array(&mut nums);
+/// assert_eq!(nums, [1, 2, 3, 4, 5, 7]);
+/// ```
+pub fn bitonic_sort_array<T, const N: usize>(nums: &mut [T; N])
+where
+    T: PartialOrd,
+{
+    let cmp = |a: &T, b: &T| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+    if N < SMALL_NETWORKS.len() {
+        __small_sort(nums, &cmp);
+    } else {
+        __bitonic_sort(nums, false, &cmp);
+    }
+}
+
+/// Merges two already sorted slices into a new sorted vector.
+///
+/// The reverse of `a` followed by `b` is a bitonic sequence (descending, then
+/// ascending), so a single run of the bitonic merge network sorts it instead of sorting
+/// the concatenation from scratch. `a` and `b` may have different lengths; the network
+/// treats the missing elements of the next power of two as padding that never moves.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_merge_sorted;
+///
+/// let merged = bitonic_merge_sorted(&[1, 4, 9], &[2, 3, 5, 8, 10]);
+/// assert_eq!(merged, vec![1, 2, 3, 4, 5, 8, 9, 10]);
+/// ```
+pub fn bitonic_merge_sorted<T>(a: &[T], b: &[T]) -> Vec<T>
+where
+    T: PartialOrd + Copy,
+{
+    let mut merged: Vec<T> = a.iter().rev().chain(b).copied().collect();
+    __bitonic_merge(&mut merged[..], false, &|x: &T, y: &T| {
+        x.partial_cmp(y).unwrap_or(Ordering::Equal)
+    });
+    merged
+}
+
+/// Merges any number of already sorted slices into a new sorted vector.
+///
+/// This is the last step of an external sort, whose chunks are sorted separately. A
+/// tournament tree over the fronts of the runs picks each next element in O(log k)
+/// comparisons for `k` runs, so merging `n` elements takes O(n log k). Runs may be empty
+/// and of any length. Equal elements come out in the order of their runs, and an
+/// element incomparable to another front is treated as equal to it.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::k_way_merge;
+///
+/// let merged = k_way_merge(&[&[1, 4, 9][..], &[], &[2, 3, 5, 8, 10], &[6]]);
+/// assert_eq!(merged, vec![1, 2, 3, 4, 5, 6, 8, 9, 10]);
+/// ```
+pub fn k_way_merge<T>(runs: &[&[T]]) -> Vec<T>
+where
+    T: PartialOrd + Copy,
+{
+    let total = runs.iter().map(|run| run.len()).sum();
+    let mut merged = Vec::with_capacity(total);
+    if runs.is_empty() {
+        return merged;
+    }
+    // leaf `size + i` holds run `i`, the leaves past the runs hold `usize::MAX`, and every
+    // inner node the run with the smaller front of its children
+    let size = runs.len().next_power_of_two();
+    let mut fronts = alloc::vec![0; runs.len()];
+    let mut tree = alloc::vec![usize::MAX; 2 * size];
+    for (i, leaf) in tree[size..size + runs.len()].iter_mut().enumerate() {
+        *leaf = i;
+    }
+    for node in (1..size).rev() {
+        tree[node] = __winner(runs, &fronts, tree[2 * node], tree[2 * node + 1]);
+    }
+    while merged.len() < total {
+        let run = tree[1];
+        merged.push(runs[run][fronts[run]]);
+        fronts[run] += 1;
+        let mut node = (size + run) / 2;
+        while node > 0 {
+            tree[node] = __winner(runs, &fronts, tree[2 * node], tree[2 * node + 1]);
+            node /= 2;
+        }
+    }
+    merged
+}
+
+// The run of `a` and `b` whose front comes first, `a` on ties. Exhausted runs and the
+// `usize::MAX` of missing ones lose against every run that has elements left.
+fn __winner<T: PartialOrd>(runs: &[&[T]], fronts: &[usize], a: usize, b: usize) -> usize {
+    let front = |run: usize| runs.get(run).and_then(|r| r.get(fronts[run]));
+    match (front(a), front(b)) {
+        (Some(x), Some(y)) if y < x => b,
+        (Some(_), _) => a,
+        (None, _) => b,
+    }
+}
+
+/// Sorts a bitonic sequence in place with the merge network alone, in O(n log n)
+/// comparisons instead of the O(n log^2 n) of a full sort.
+///
+/// The output is ascending, or descending if `reverse` is set. When the length is a
+/// power of two, `nums` may be any bitonic sequence (see
+/// [`is_bitonic`](crate::validate::is_bitonic)). Other lengths need the shape the sort
+/// builds before merging: first non-increasing, then non-decreasing for an ascending
+/// output, and the other way round for a descending one. Debug builds assert this
+/// precondition; release builds produce an unspecified order if it does not hold.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_merge;
+///
+/// let mut nums = [9, 6, 2, 1, 3, 4, 8];
+/// bitonic_merge(&mut nums, false);
+/// assert_eq!(nums, [1, 2, 3, 4, 6, 8, 9]);
+///
+/// let mut nums = [1, 5, 8, 7, 3, 2];
+/// bitonic_merge(&mut nums, true);
+/// assert_eq!(nums, [8, 7, 5, 3, 2, 1]);
+/// ```
+pub fn bitonic_merge<T>(nums: &mut [T], reverse: bool)
+where
+    T: PartialOrd,
+{
+    debug_assert!(
+        __is_mergeable(nums, reverse),
+        "the input of `bitonic_merge` is not bitonic"
+    );
+    __bitonic_merge(nums, reverse, &|a: &T, b: &T| {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Turns `nums` into the bitonic sequence that [`bitonic_merge`] sorts, by sorting its
+/// two halves in opposite directions.
+///
+/// This is the first step of [`bitonic_sort`], which is `build_bitonic` followed by
+/// `bitonic_merge` in the same direction. For an ascending merge (`reverse == false`) the
+/// first half is sorted descending and the second ascending; with `reverse` it is the
+/// other way round. Starting against the final direction is what lets the merge handle
+/// lengths that are not a power of two.
 ///
 /// # Examples
 ///
 /// ```
-/// use bitonic_sort::bitonic_serial::bitonic_sort;
+/// use bitonic_sort::bitonic_serial::{bitonic_merge, build_bitonic};
+/// use bitonic_sort::validate::is_bitonic;
 ///
-/// let mut nums = vec![4, 2, 7, 1, 5];
-/// bitonic_sort(&mut nums);
-/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// let mut nums = [4, 2, 7, 1, 5, 3, 6];
+/// build_bitonic(&mut nums, false);
+/// assert_eq!(nums, [7, 4, 2, 1, 3, 5, 6]);
+/// assert!(is_bitonic(&nums));
+/// bitonic_merge(&mut nums, false);
+/// assert_eq!(nums, [1, 2, 3, 4, 5, 6, 7]);
 /// ```
-use std::cell::Cell;
+pub fn build_bitonic<T>(nums: &mut [T], reverse: bool)
+where
+    T: PartialOrd,
+{
+    let cmp = |a: &T, b: &T| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+    let len = nums.len();
+    __bitonic_sort(&mut nums[..len / 2], !reverse, &cmp);
+    __bitonic_sort(&mut nums[len / 2..], reverse, &cmp);
+}
 
-pub fn bitonic_sort<T>(nums: &mut Vec<T>)
+// Whether the merge network sorts `nums` in the given direction.
+fn __is_mergeable<T: PartialOrd>(nums: &[T], reverse: bool) -> bool {
+    if nums.len().is_power_of_two() {
+        return validate::is_bitonic(nums);
+    }
+    // moving in the output's direction once, it may not turn back
+    let forward = if reverse {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    };
+    let mut turned = false;
+    nums.windows(2)
+        .all(|pair| match pair[0].partial_cmp(&pair[1]) {
+            None => false,
+            Some(Ordering::Equal) => true,
+            Some(ord) if ord == forward => {
+                turned = true;
+                true
+            }
+            Some(_) => !turned,
+        })
+}
+
+/// Sorts the given vector with the general bitonic network and returns the number of
+/// comparisons it made.
+///
+/// The network compares the same pairs whatever the input, so for a length of `2^k`
+/// the count is always `k(k+1)/2 * 2^(k-1)`. Other lengths run the network without its
+/// padding and count exactly `bitonic_network(len).len()` comparisons. This is the
+/// network of [`bitonic_sort_by`] at every length; [`bitonic_sort`] sorts up to eight
+/// elements with smaller networks and makes fewer comparisons there, 19 instead of 24
+/// for eight. The counting lives in this function's comparator only, so the other sorts
+/// pay nothing for it.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_counted;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5, 3, 6, 8];
+/// assert_eq!(bitonic_sort_counted(&mut nums), 24);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+/// ```
+pub fn bitonic_sort_counted<T>(nums: &mut Vec<T>) -> usize
 where
-    T: PartialOrd + Copy,
+    T: PartialOrd,
+{
+    let count = Cell::new(0);
+    __bitonic_sort(&mut nums[..], false, &|a: &T, b: &T| {
+        count.set(count.get() + 1);
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+    count.get()
+}
+
+/// Sorts the given vector with the general bitonic network, reporting every
+/// compare-exchange to `trace` as it happens.
+///
+/// `trace` receives the two indices compared, lower first, and whether their elements
+/// were swapped. The events arrive in the order of [`bitonic_network`], one per
+/// comparator, which makes them suitable for animating or stepping through the network.
+/// Up to eight elements, they are not the comparisons of [`bitonic_sort`], which uses
+/// smaller networks there.
+/// The tracing lives in this function only, so the other sorts pay nothing for it.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_sort_traced;
+///
+/// let mut nums = vec![2, 1];
+/// let mut events = Vec::new();
+/// bitonic_sort_traced(&mut nums, |i, j, swapped| events.push((i, j, swapped)));
+/// assert_eq!(nums, vec![1, 2]);
+/// assert_eq!(events, vec![(0, 1, true)]);
+/// ```
+pub fn bitonic_sort_traced<T, F>(nums: &mut Vec<T>, mut trace: F)
+where
+    T: PartialOrd,
+    F: FnMut(usize, usize, bool),
 {
-    if nums.is_empty() {
+    for (i, j, reverse) in bitonic_network(nums.len()) {
+        let ord = nums[i].partial_cmp(&nums[j]).unwrap_or(Ordering::Equal);
+        let swapped = __should_swap(ord, reverse);
+        if swapped {
+            nums.swap(i, j);
+        }
+        trace(i, j, swapped);
+    }
+}
+
+/// Returns the compare-exchange operations of the general serial bitonic network for
+/// `len` elements.
+///
+/// Each `(i, j, reverse)` triple, with `i < j`, compares the elements at `i` and `j` and
+/// swaps them if `nums[i] > nums[j]`, or if `nums[i] < nums[j]` when `reverse` is set,
+/// exactly as [`bitonic_sort_by`] does. Equal elements are never swapped. The triples are
+/// in execution order, so replaying them on any backend sorts the data. For lengths that
+/// are not a power of two, the comparators that would touch padding are left out,
+/// matching the sort, which never materializes the padding. [`bitonic_sort`] runs this
+/// network too, except for up to eight elements, which it sorts with smaller networks.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_network;
+///
+/// let mut nums = [3, 1, 2];
+/// for (i, j, reverse) in bitonic_network(nums.len()) {
+///     if (!reverse && nums[i] > nums[j]) || (reverse && nums[i] < nums[j]) {
+///         nums.swap(i, j);
+///     }
+/// }
+/// assert_eq!(nums, [1, 2, 3]);
+/// ```
+pub fn bitonic_network(len: usize) -> Vec<(usize, usize, bool)> {
+    let mut network = Vec::new();
+    __network_sort(0, len, false, &mut network);
+    network
+}
+
+fn __network_sort(lo: usize, len: usize, reverse: bool, network: &mut Vec<(usize, usize, bool)>) {
+    if len <= 1 {
         return;
     }
-    let origin_len = nums.len();
-    if !origin_len.is_power_of_two() {
-        let max = *nums.iter().fold(
-            nums.first().unwrap(),
-            |max, x| if max.ge(x) { max } else { x },
-        );
-        nums.resize(origin_len.next_power_of_two(), max);
+    __network_sort(lo, len / 2, !reverse, network);
+    __network_sort(lo + len / 2, len - len / 2, reverse, network);
+    __network_merge(lo, len, reverse, network);
+}
+
+fn __network_merge(lo: usize, len: usize, reverse: bool, network: &mut Vec<(usize, usize, bool)>) {
+    if len <= 1 {
+        return;
+    }
+    let stride = __stride(len);
+    network.extend((lo..lo + len - stride).map(|i| (i, i + stride, reverse)));
+    __network_merge(lo, stride, reverse, network);
+    __network_merge(lo + stride, len - stride, reverse, network);
+}
+
+// Merges a bitonic sequence of any length. The first comparator stride is the largest
+// power of two below `len`, and only the first `len - stride` elements have a partner:
+// the missing ones behave as if the sequence were padded with elements that never move.
+// Each halved stride then compares within blocks of twice its length, which are the
+// halves the recursive formulation would merge, so no recursion is needed.
+pub(crate) fn __bitonic_merge<T, F>(nums: &mut [T], reverse: bool, cmp: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let mut stride = __stride(nums.len());
+    while stride > 0 {
+        // a block longer than the slice is the whole slice
+        for block in nums.chunks_mut(stride.saturating_mul(2)) {
+            if block.len() <= stride {
+                continue;
+            }
+            let (lo, hi) = block.split_at_mut(stride);
+            for (num1, num2) in lo.iter_mut().zip(hi.iter_mut()) {
+                if __should_swap(cmp(num1, num2), reverse) {
+                    mem::swap(num1, num2);
+                }
+            }
+        }
+        stride /= 2;
     }
+}
+
+// The first comparator stride of the merge of `len` elements: the largest power of two
+// below `len`, or zero if there is nothing to merge. Unlike `next_power_of_two() / 2`
+// this cannot overflow, even for the `usize::MAX` elements a slice of zero-sized values
+// may hold.
+pub(crate) fn __stride(len: usize) -> usize {
+    match len {
+        0 | 1 => 0,
+        len => 1 << (len - 1).ilog2(),
+    }
+}
 
-    __bitonic_sort(&mut nums[..], false);
-    nums.truncate(origin_len);
+// Equal (and incomparable) elements never swap, in either direction, so the elements'
+// identities in the output only depend on the network and not on how it was scheduled.
+pub(crate) fn __should_swap(ord: Ordering, reverse: bool) -> bool {
+    ord == if reverse {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
 }
 
-fn __bitonic_merge<T>(nums: &mut [T], reverse: bool)
+// Sorts `nums` bottom-up: the network splits every part of more than one element into
+// halves of `len / 2` and `len - len / 2` elements, sorts the first half against and the
+// second half in the final direction so that the shorter half lines up with the tail of
+// the bitonic sequence the merge expects, and merges. The parts at one depth of that
+// split are disjoint, so merging all of them level by level, deepest first, runs the
+// same comparators as the recursion without its call stack.
+pub(crate) fn __bitonic_sort<T, F>(nums: &mut [T], reverse: bool, cmp: &F)
 where
-    T: PartialOrd + Copy,
+    F: Fn(&T, &T) -> Ordering,
 {
     let len = nums.len();
-    let slice = Cell::from_mut(&mut nums[..]).as_slice_of_cells();
-    for (num1, num2) in slice[..len / 2].iter().zip(slice[len / 2..].iter()) {
-        if (num1.get() > num2.get()) ^ reverse {
-            Cell::swap(num1, num2);
+    if len <= 1 {
+        return;
+    }
+    // parts of two or more elements exist down to the depth where `2^depth < len`
+    for depth in (0..=(len - 1).ilog2()).rev() {
+        for part in 0..1 << depth {
+            let (lo, part_len, part_reverse) = __part(len, reverse, depth, part);
+            if part_len > 1 {
+                __bitonic_merge(&mut nums[lo..lo + part_len], part_reverse, cmp);
+            }
         }
     }
 }
 
-fn __bitonic_sort<T>(nums: &mut [T], reverse: bool)
+// Calls `exchange(i, j, reverse)` for every comparator of the network of `len` elements,
+// in the order `__bitonic_sort` runs them, for storage that cannot be borrowed as a
+// slice. The pairs of a merge are the ones `__bitonic_merge` zips within each block.
+pub(crate) fn __for_each_comparator<F>(len: usize, reverse: bool, mut exchange: F)
 where
-    T: PartialOrd + Copy,
+    F: FnMut(usize, usize, bool),
 {
-    let len = nums.len();
     if len <= 1 {
         return;
     }
-    __bitonic_sort(&mut nums[..len / 2], false);
-    __bitonic_sort(&mut nums[len / 2..], true);
-    let mut size = len;
-    while size > 1 {
-        for i in 0..len / size {
-            __bitonic_merge(&mut nums[i * size..(i + 1) * size], reverse);
+    for depth in (0..=(len - 1).ilog2()).rev() {
+        for part in 0..1 << depth {
+            let (lo, part_len, part_reverse) = __part(len, reverse, depth, part);
+            let mut stride = __stride(part_len);
+            while stride > 0 {
+                for block in (0..part_len).step_by(stride.saturating_mul(2)) {
+                    for i in block..block.saturating_add(stride).min(part_len - stride) {
+                        exchange(lo + i, lo + i + stride, part_reverse);
+                    }
+                }
+                stride /= 2;
+            }
+        }
+    }
+}
+
+// The start, length and direction of the `part`th of the `2^depth` parts at `depth` of
+// the split of `len` elements, found by following the bits of `part` from the top: a
+// zero bit takes the first half, which is sorted against its parent's direction. Past
+// a part of a single element the halves are empty or that element again.
+fn __part(len: usize, reverse: bool, depth: u32, part: usize) -> (usize, usize, bool) {
+    let (mut lo, mut len, mut reverse) = (0, len, reverse);
+    for level in (0..depth).rev() {
+        if part >> level & 1 == 0 {
+            len /= 2;
+            reverse = !reverse;
+        } else {
+            lo += len / 2;
+            len -= len / 2;
+        }
+    }
+    (lo, len, reverse)
+}
+
+// The smallest known sorting networks for up to eight elements, indexed by length. Each
+// comparator puts the smaller element at the lower index.
+const SMALL_NETWORKS: [&[(usize, usize)]; 9] = [
+    &[],
+    &[],
+    &[(0, 1)],
+    &[(0, 2), (0, 1), (1, 2)],
+    &[(0, 1), (2, 3), (0, 2), (1, 3), (1, 2)],
+    &[
+        (0, 3),
+        (1, 4),
+        (0, 2),
+        (1, 3),
+        (0, 1),
+        (2, 4),
+        (1, 2),
+        (3, 4),
+        (2, 3),
+    ],
+    &[
+        (0, 5),
+        (1, 3),
+        (2, 4),
+        (1, 2),
+        (3, 4),
+        (0, 3),
+        (2, 5),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (1, 2),
+        (3, 4),
+    ],
+    &[
+        (0, 6),
+        (2, 3),
+        (4, 5),
+        (0, 2),
+        (1, 4),
+        (3, 6),
+        (0, 1),
+        (2, 5),
+        (3, 4),
+        (1, 2),
+        (4, 6),
+        (2, 3),
+        (4, 5),
+        (1, 2),
+        (3, 4),
+        (5, 6),
+    ],
+    &[
+        (0, 2),
+        (1, 3),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+        (0, 1),
+        (2, 3),
+        (4, 5),
+        (6, 7),
+        (2, 4),
+        (3, 5),
+        (1, 4),
+        (3, 6),
+        (1, 2),
+        (3, 4),
+        (5, 6),
+    ],
+];
+
+// Sorts fewer than `SMALL_NETWORKS.len()` elements in ascending order with the fixed
+// network for their length. Like the bitonic network, equal elements are never swapped.
+// The serial leaves of the parallel sorts do not use it: those of `bitonic_parallel` sort
+// in the direction of their `reverse` flag and are rarely this short, and those of
+// `parallel_sort` go to the standard library's sorts, which handle short inputs anyway.
+fn __small_sort<T, F>(nums: &mut [T], cmp: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    for &(i, j) in SMALL_NETWORKS[nums.len()] {
+        if cmp(&nums[i], &nums[j]) == Ordering::Greater {
+            nums.swap(i, j);
         }
-        size /= 2;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{gen_few_unique, gen_sorted, gen_tagged, gen_uniform, Tagged, SEED};
 
     #[test]
     fn test_bitonic_sort_ascending() {
@@ -127,4 +1222,662 @@ mod tests {
         bitonic_sort(&mut nums);
         assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
     }
+
+    #[test]
+    fn test_bitonic_sort_by_field() {
+        let mut nums = vec![(4, 'a'), (2, 'b'), (7, 'c'), (1, 'd'), (5, 'e')];
+        bitonic_sort_by(&mut nums, |a, b| a.1.cmp(&b.1).reverse());
+        assert_eq!(nums, vec![(5, 'e'), (1, 'd'), (7, 'c'), (2, 'b'), (4, 'a')]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_comparator_order() {
+        let mut nums = vec![4, 2, 7, 1, 5];
+        bitonic_sort_by(&mut nums, |a, b| b.cmp(a));
+        assert_eq!(nums, vec![7, 5, 4, 2, 1]);
+
+        // an order unlike the natural one, by the last digit and then descending, on
+        // lengths that are mostly not a power of two
+        let cmp = |a: &u32, b: &u32| (a % 10).cmp(&(b % 10)).then(b.cmp(a));
+        for len in 0..70 {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort_by(cmp);
+            bitonic_sort_by(&mut nums, cmp);
+            assert!(nums == expected, "unsorted output for len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_desc() {
+        let mut nums = vec![4, 2, 7, 1, 5];
+        bitonic_sort_desc(&mut nums);
+        assert_eq!(nums, vec![7, 5, 4, 2, 1]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_desc_power_of_two() {
+        let mut nums = vec![4, 2, 7, 1, 5, 3, 6, 8];
+        bitonic_sort_desc(&mut nums);
+        assert_eq!(nums, vec![8, 7, 6, 5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_slice() {
+        let mut nums = [4, 2, 7, 1, 5, 3, 6, 4, 2, 7];
+        bitonic_sort_slice(&mut nums[2..]);
+        assert_eq!(nums, [4, 2, 1, 2, 3, 4, 5, 6, 7, 7]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_strings() {
+        let mut nums: Vec<String> = ["pear", "apple", "fig", "kiwi", "banana"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        bitonic_sort(&mut nums);
+        assert_eq!(nums, vec!["apple", "banana", "fig", "kiwi", "pear"]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_every_length() {
+        for len in 0..100 {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            bitonic_sort(&mut nums);
+            assert!(nums == expected, "unsorted output for len = {}", len);
+            expected.reverse();
+            bitonic_sort_desc(&mut nums);
+            assert!(
+                nums == expected,
+                "unsorted descending output for len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_key() {
+        let mut nums = vec![-4, 2, -7, 1, 5, -3, 6];
+        bitonic_sort_by_key(&mut nums, |x: &i32| x.abs());
+        assert_eq!(nums, vec![1, 2, -3, -4, 5, 6, -7]);
+
+        // the by-key sorts take slices, so part of an array sorts without a `Vec`
+        let mut nums = [9, -4, 2, -7, 1, 0];
+        bitonic_sort_by_key(&mut nums[1..5], |x: &i32| x.abs());
+        assert_eq!(nums, [9, 1, 2, -4, -7, 0]);
+        bitonic_sort_by_key_into(&mut nums[..3], |x: &i32| -x);
+        assert_eq!(nums, [9, 2, 1, -4, -7, 0]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_key_counts_key_calls() {
+        let calls = Cell::new(0);
+        let mut nums: Vec<String> = ["ccc", "a", "bb", "dddd", "a"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        bitonic_sort_by_key(&mut nums, |s| {
+            calls.set(calls.get() + 1);
+            s.len()
+        });
+        assert_eq!(nums, vec!["a", "a", "bb", "ccc", "dddd"]);
+        assert_eq!(calls.get(), 5);
+    }
+
+    #[test]
+    fn test_bitonic_sorted() {
+        let nums = [4, 2, 7, 1, 5];
+        assert_eq!(bitonic_sorted(&nums), vec![1, 2, 4, 5, 7]);
+        assert_eq!(nums, [4, 2, 7, 1, 5]);
+    }
+
+    #[test]
+    fn test_bitonic_merge_sorted() {
+        assert_eq!(
+            bitonic_merge_sorted(&[1, 3, 5, 7], &[2, 4, 6, 8]),
+            vec![1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        assert_eq!(bitonic_merge_sorted::<i32>(&[], &[]), vec![]);
+        assert_eq!(bitonic_merge_sorted(&[], &[1, 2]), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_bitonic_merge_sorted_uneven_lengths() {
+        for len_a in 0..20u32 {
+            for len_b in 0..20u32 {
+                let a: Vec<u32> = (0..len_a).map(|x| x * 3).collect();
+                let b: Vec<u32> = (0..len_b).map(|x| x * 2 + 1).collect();
+                let mut expected = [a.clone(), b.clone()].concat();
+                expected.sort();
+                assert_eq!(bitonic_merge_sorted(&a, &b), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_network_size() {
+        for k in 1..10u32 {
+            let len = 1 << k;
+            let expected = (k * (k + 1) / 2) as usize * (len / 2);
+            assert_eq!(bitonic_network(len).len(), expected);
+        }
+        assert!(bitonic_network(0).is_empty());
+        assert!(bitonic_network(1).is_empty());
+    }
+
+    #[test]
+    fn test_bitonic_network_matches_sort() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            let mut replayed = nums.clone();
+            for (i, j, reverse) in bitonic_network(replayed.len()) {
+                if __should_swap(replayed[i].cmp(&replayed[j]), reverse) {
+                    replayed.swap(i, j);
+                }
+            }
+            let mut sorted = nums.clone();
+            bitonic_sort(&mut sorted);
+            assert_eq!(replayed, sorted);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_traced() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            let mut traced = nums.clone();
+            let mut events = Vec::new();
+            bitonic_sort_traced(&mut traced, |i, j, swapped| events.push((i, j, swapped)));
+
+            let network = bitonic_network(nums.len());
+            assert_eq!(events.len(), network.len(), "length {}", len);
+            let mut replayed = nums.clone();
+            for (&(i, j, swapped), &(a, b, _)) in events.iter().zip(&network) {
+                assert_eq!((i, j), (a, b), "length {}", len);
+                if swapped {
+                    replayed.swap(i, j);
+                }
+            }
+            let mut sorted = nums;
+            bitonic_sort(&mut sorted);
+            assert_eq!(traced, sorted, "length {}", len);
+            assert_eq!(replayed, sorted, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_key_into_floats() {
+        let mut nums: Vec<f64> = gen_few_unique(200, 9)
+            .into_iter()
+            .map(|x| match x {
+                0 => f64::NAN,
+                1 => -f64::NAN,
+                2 => -0.0,
+                n => n as f64 - 5.0,
+            })
+            .collect();
+        let mut expected = nums.clone();
+        expected.sort_by(f64::total_cmp);
+        bitonic_sort_by_key_into(&mut nums, f64_total_key);
+        let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&nums), bits(&expected));
+    }
+
+    #[test]
+    fn test_bitonic_sort_ord() {
+        let mut nums: Vec<String> = gen_few_unique(100, 1000)
+            .iter()
+            .map(u32::to_string)
+            .collect();
+        let mut expected = nums.clone();
+        expected.sort();
+        bitonic_sort_ord(&mut nums);
+        assert_eq!(nums, expected);
+    }
+
+    #[test]
+    fn test_bitonic_sort_counted() {
+        for k in 0..12 {
+            let mut nums = gen_few_unique(1 << k, 100);
+            let expected = k * (k + 1) / 2 * (1 << k) / 2;
+            assert_eq!(bitonic_sort_counted(&mut nums), expected);
+        }
+        for len in [3, 100, 1000] {
+            let mut nums = gen_sorted(len);
+            assert_eq!(bitonic_sort_counted(&mut nums), bitonic_network(len).len());
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_lex() {
+        // few distinct values per column, so most rows are decided by a later column
+        let mut rows: Vec<[u32; 4]> = gen_uniform(500, SEED)
+            .into_iter()
+            .zip(0..)
+            .map(|(h, x)| [h % 3, (h >> 8) % 3, (h >> 16) % 3, x])
+            .collect();
+        let mut expected = rows.clone();
+        expected.sort();
+        bitonic_sort_lex(&mut rows);
+        assert_eq!(rows, expected);
+    }
+
+    fn divides(a: &u32, b: &u32) -> Option<Ordering> {
+        match (b.is_multiple_of(*a), a.is_multiple_of(*b)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_partial_chain() {
+        let mut nums = vec![32, 4, 1, 64, 8, 2, 16];
+        bitonic_sort_partial(&mut nums, divides);
+        assert_eq!(nums, vec![1, 2, 4, 8, 16, 32, 64]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_partial_incomparable_stay() {
+        let mut nums = vec![11, 3, 7, 2, 13, 5];
+        bitonic_sort_partial(&mut nums, divides);
+        assert_eq!(nums, vec![11, 3, 7, 2, 13, 5]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_partial_divisibility() {
+        let mut nums: Vec<u32> = (1..=12).rev().collect();
+        bitonic_sort_partial(&mut nums, divides);
+        let mut sorted = nums.clone();
+        sorted.sort();
+        assert_eq!(sorted, (1..=12).collect::<Vec<_>>());
+        // 1 divides everything, so it is comparable to every element and must lead
+        assert_eq!(nums[0], 1);
+    }
+
+    #[test]
+    fn test_bitonic_merge() {
+        for len in 0..70 {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            // a valley for the ascending merge, a peak for the descending one
+            let mid = len / 3;
+            nums[..mid].sort_by(|a, b| b.cmp(a));
+            nums[mid..].sort();
+            let mut peak: Vec<u32> = nums.iter().map(|x| 50 - x).collect();
+            bitonic_merge(&mut nums, false);
+            assert!(nums == expected, "ascending length {}", len);
+            bitonic_merge(&mut peak, true);
+            assert!(
+                peak.iter().map(|x| 50 - x).eq(expected),
+                "descending length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_merge_power_of_two_rotation() {
+        let mut nums = [5, 7, 8, 6, 4, 2, 1, 3];
+        bitonic_merge(&mut nums, false);
+        assert_eq!(nums, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not bitonic")]
+    fn test_bitonic_merge_rejects_non_bitonic() {
+        bitonic_merge(&mut [1, 3, 2, 4, 0], false);
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_borrowed_key_prefix() {
+        let mut words: Vec<String> = ["pear", "apple", "fig", "peach", "apricot"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        bitonic_sort_by_borrowed_key::<_, str, str, _>(&mut words, |word| &word[..2]);
+        let prefixes: Vec<&str> = words.iter().map(|word| &word[..2]).collect();
+        assert_eq!(prefixes, ["ap", "ap", "fi", "pe", "pe"]);
+        words.sort();
+        assert_eq!(words, ["apple", "apricot", "fig", "peach", "pear"]);
+    }
+
+    #[test]
+    fn test_sort_is_stable() {
+        for len in [0, 1, 7, 100, 1000] {
+            let mut nums = gen_tagged(len, 10);
+            let mut expected = nums.clone();
+            expected.sort_by_key(|record| record.0);
+            sort(&mut nums);
+            assert!(nums == expected, "unstable output for length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_dyn() {
+        type Cmp<'a> = &'a dyn Fn(&u32, &u32) -> Ordering;
+        let comparators: [Cmp; 2] = [&|a, b| a.cmp(b), &|a, b| b.cmp(a)];
+        for (i, cmp) in comparators.into_iter().enumerate() {
+            let mut nums = gen_few_unique(100, 50);
+            let mut expected = nums.clone();
+            expected.sort_by(cmp);
+            bitonic_sort_dyn(&mut nums, cmp);
+            assert!(nums == expected, "comparator {}", i);
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_pairs() {
+        let mut nums: Vec<(u32, u32)> = gen_few_unique(1000, 10)
+            .into_iter()
+            .zip((1..=1000).rev())
+            .collect();
+        let mut expected = nums.clone();
+        expected.sort_by_key(|pair| pair.0);
+        bitonic_sort_pairs(&mut nums);
+        assert_eq!(nums, expected);
+
+        // neither the keys nor the values are `Copy`
+        let mut names: Vec<(String, Vec<u32>)> = gen_few_unique(100, 10)
+            .into_iter()
+            .zip(0..)
+            .map(|(key, x)| (format!("{}", key), vec![x]))
+            .collect();
+        let mut expected = names.clone();
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        bitonic_sort_pairs(&mut names);
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_stride() {
+        for len in 0..2000usize {
+            assert_eq!(__stride(len), len.next_power_of_two() / 2, "length {}", len);
+        }
+        assert_eq!(__stride(usize::MAX), 1 << (usize::BITS - 1));
+        assert_eq!(
+            __stride((1 << (usize::BITS - 1)) + 1),
+            1 << (usize::BITS - 1)
+        );
+    }
+
+    #[test]
+    fn test_build_bitonic_then_merge() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            for reverse in [false, true] {
+                let mut built = nums.clone();
+                build_bitonic(&mut built, reverse);
+                assert!(__is_mergeable(&built, reverse), "length {}", len);
+                bitonic_merge(&mut built, reverse);
+                let mut expected = nums.clone();
+                __bitonic_sort(&mut expected, reverse, &u32::cmp);
+                assert!(built == expected, "length {} reverse {}", len, reverse);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_refs() {
+        let words: Vec<String> = gen_few_unique(37, 50)
+            .iter()
+            .map(|x| format!("word-{}", x))
+            .collect();
+        let mut refs: Vec<&String> = words.iter().collect();
+        bitonic_sort_refs(&mut refs);
+        let mut expected = words.clone();
+        expected.sort();
+        assert_eq!(refs, expected.iter().collect::<Vec<_>>());
+        // every reference still points into `words`, nothing was cloned
+        assert!(refs
+            .iter()
+            .all(|r| words.iter().any(|w| core::ptr::eq(*r, w))));
+        assert_eq!(refs.len(), words.len());
+    }
+
+    // The recursive formulation the bottom-up network replaces.
+    fn __recursive_sort<T, F>(nums: &mut [T], reverse: bool, cmp: &F)
+    where
+        F: Fn(&T, &T) -> Ordering,
+    {
+        fn merge<T, F>(nums: &mut [T], reverse: bool, cmp: &F)
+        where
+            F: Fn(&T, &T) -> Ordering,
+        {
+            let stride = __stride(nums.len());
+            if stride == 0 {
+                return;
+            }
+            let (lo, hi) = nums.split_at_mut(stride);
+            for (num1, num2) in lo.iter_mut().zip(hi.iter_mut()) {
+                if __should_swap(cmp(num1, num2), reverse) {
+                    mem::swap(num1, num2);
+                }
+            }
+            merge(lo, reverse, cmp);
+            merge(hi, reverse, cmp);
+        }
+        let len = nums.len();
+        if len <= 1 {
+            return;
+        }
+        __recursive_sort(&mut nums[..len / 2], !reverse, cmp);
+        __recursive_sort(&mut nums[len / 2..], reverse, cmp);
+        merge(nums, reverse, cmp);
+    }
+
+    #[test]
+    fn test_iterative_matches_recursive() {
+        // the keys repeat, and the indices tell equal keys apart, so the two must move
+        // every element to the same place, not just produce the same keys
+        let cmp = |x: &Tagged, y: &Tagged| x.cmp(y);
+        for len in 0..300 {
+            for reverse in [false, true] {
+                let nums = gen_tagged(len, 17);
+                let mut iterative = nums.clone();
+                __bitonic_sort(&mut iterative, reverse, &cmp);
+                let mut recursive = nums;
+                __recursive_sort(&mut recursive, reverse, &cmp);
+                assert!(
+                    iterative == recursive,
+                    "outputs differ for len = {}, reverse = {}",
+                    len,
+                    reverse
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_cached_key() {
+        use alloc::format;
+        use alloc::string::String;
+
+        // neither the elements nor the keys are `Copy`, and every key is computed once
+        let mut nums: Vec<Vec<u32>> = gen_tagged(100, 10)
+            .into_iter()
+            .map(|record| alloc::vec![record.0, record.1])
+            .collect();
+        let mut expected = nums.clone();
+        let calls = Cell::new(0);
+        bitonic_sort_by_cached_key(&mut nums, |x| -> String {
+            calls.set(calls.get() + 1);
+            format!("{}", x[0])
+        });
+        assert_eq!(calls.get(), 100);
+        // ties keep their input order, which the second field records
+        expected.sort_by_key(|x| x[0]);
+        assert_eq!(nums, expected);
+    }
+
+    // All orderings of `0..len`, by swapping each position with every later one.
+    fn __permutations(len: usize) -> Vec<Vec<u32>> {
+        fn permute(prefix: usize, nums: &mut Vec<u32>, out: &mut Vec<Vec<u32>>) {
+            if prefix == nums.len() {
+                out.push(nums.clone());
+                return;
+            }
+            for i in prefix..nums.len() {
+                nums.swap(prefix, i);
+                permute(prefix + 1, nums, out);
+                nums.swap(prefix, i);
+            }
+        }
+        let mut out = Vec::new();
+        permute(0, &mut gen_sorted(len), &mut out);
+        out
+    }
+
+    #[test]
+    fn test_small_networks_sort_all_permutations() {
+        for (len, network) in SMALL_NETWORKS.iter().enumerate() {
+            assert!(network.iter().all(|&(i, j)| i < j && j < len));
+            let sorted = gen_sorted(len);
+            for permutation in __permutations(len) {
+                let mut nums = permutation.clone();
+                __small_sort(&mut nums, &u32::cmp);
+                assert!(
+                    nums == sorted,
+                    "network {} failed on {:?}",
+                    len,
+                    permutation
+                );
+
+                let mut nums = permutation.clone();
+                bitonic_sort(&mut nums);
+                assert!(nums == sorted, "length {} failed on {:?}", len, permutation);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_networks_with_duplicates() {
+        // by the 0-1 principle, every input of zeros and ones covers all the others
+        for (len, network) in SMALL_NETWORKS.iter().enumerate() {
+            for bits in 0..1u32 << len {
+                let mut nums: Vec<u32> = (0..len).map(|i| bits >> i & 1).collect();
+                let mut expected = nums.clone();
+                expected.sort();
+                __small_sort(&mut nums, &u32::cmp);
+                assert!(nums == expected, "network {} failed on {:b}", len, bits);
+            }
+            assert!(network.len() <= bitonic_network(len).len());
+        }
+    }
+
+    #[test]
+    fn test_k_way_merge() {
+        assert!(k_way_merge::<u32>(&[]).is_empty());
+        assert!(k_way_merge::<u32>(&[&[], &[]]).is_empty());
+
+        for k in 1..12 {
+            // lengths from empty to a few hundred, so that some runs run dry early
+            let lens = gen_few_unique(k, 300);
+            let runs: Vec<Vec<u32>> = (0..k)
+                .map(|i| {
+                    let len = lens[i] as usize * (i % 3);
+                    let mut run: Vec<u32> = gen_uniform(len, SEED + i as u64)
+                        .into_iter()
+                        .map(|x| x % 1000)
+                        .collect();
+                    run.sort();
+                    run
+                })
+                .collect();
+            let slices: Vec<&[u32]> = runs.iter().map(|run| &run[..]).collect();
+            let mut expected = runs.concat();
+            expected.sort();
+            assert!(
+                k_way_merge(&slices) == expected,
+                "unsorted output for k = {}",
+                k
+            );
+        }
+    }
+
+    #[test]
+    fn test_k_way_merge_keeps_run_order() {
+        // the tag records the run
+        let runs: Vec<Vec<Tagged>> = (0..5)
+            .map(|run| (0..10).map(|x| Tagged(x / 3, run)).collect())
+            .collect();
+        let slices: Vec<&[Tagged]> = runs.iter().map(|run| &run[..]).collect();
+        let merged = k_way_merge(&slices);
+        for pair in merged.windows(2) {
+            assert!(
+                pair[0].0 < pair[1].0 || (pair[0].0 == pair[1].0 && pair[0].1 <= pair[1].1),
+                "{:?} before {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_by_mut() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+
+            // a memoizing comparator that records every pair it has seen
+            let mut seen = alloc::collections::BTreeSet::new();
+            let mut calls = 0;
+            let mut sorted = nums;
+            bitonic_sort_by_mut(&mut sorted, |a, b| {
+                calls += 1;
+                seen.insert((*a, *b));
+                a.cmp(b)
+            });
+            assert!(sorted == expected, "unsorted output for len = {}", len);
+            assert_eq!(calls, bitonic_network(len).len());
+            assert!(seen.len() <= calls);
+        }
+    }
+
+    #[test]
+    fn test_for_each_comparator_matches_sort() {
+        for len in 0..70 {
+            let nums = gen_few_unique(len, 50);
+            for reverse in [false, true] {
+                let mut indexed = nums.clone();
+                let mut count = 0;
+                __for_each_comparator(indexed.len(), reverse, |i, j, reverse| {
+                    count += 1;
+                    if __should_swap(indexed[i].cmp(&indexed[j]), reverse) {
+                        indexed.swap(i, j);
+                    }
+                });
+                let mut sorted = nums.clone();
+                __bitonic_sort(&mut sorted, reverse, &u32::cmp);
+                assert!(indexed == sorted, "different output for len = {}", len);
+                assert_eq!(count, bitonic_network(len).len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_sort_array() {
+        fn check<const N: usize>() {
+            let mut nums: [u32; N] = gen_few_unique(N, 50).try_into().unwrap();
+            let mut expected = nums;
+            expected.sort();
+            bitonic_sort_array(&mut nums);
+            assert!(nums == expected, "unsorted output for N = {}", N);
+        }
+        check::<0>();
+        check::<1>();
+        check::<2>();
+        check::<5>();
+        check::<8>();
+        check::<9>();
+        check::<16>();
+        check::<100>();
+        check::<1000>();
+    }
 }
@@ -0,0 +1,166 @@
+//! This module provides a serial bitonic sort for `f32` and `f64` that compare-exchanges
+//! several lanes at once, behind the `simd` feature.
+//!
+//! The half-cleaner loops of the merge run on `wide` vectors: eight `f32` or four `f64`
+//! pairs are compared and swapped per step with a compare mask and a blend. Strides
+//! shorter than a few vectors, and the leftover pairs of a pass, use the scalar code of
+//! [`bitonic_serial`]. The swap condition is the scalar one lane for lane, so the output
+//! is bit-identical to [`bitonic_serial::bitonic_sort`], including `NaN`s and signed
+//! zeros.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::bitonic_simd::bitonic_sort;
+//!
+//! let mut nums = vec![3.5f32, -1.0, 2.0, 0.5];
+//! bitonic_sort(&mut nums);
+//! assert_eq!(nums, vec![-1.0, 0.5, 2.0, 3.5]);
+//! ```
+
+// the `Vec` entry point mirrors `bitonic_serial`
+#![allow(clippy::ptr_arg)]
+
+use crate::bitonic_serial;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use wide::{f32x8, f64x4, CmpGt, CmpLt};
+
+// Merges with a shorter stride, and sorts of fewer elements, run the scalar network.
+const SIMD_MIN_STRIDE: usize = 32;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The element types with a vectorized compare-exchange, `f32` and `f64`.
+pub trait SimdElement: PartialOrd + Copy + sealed::Sealed {
+    #[doc(hidden)]
+    fn __half_clean(lo: &mut [Self], hi: &mut [Self], reverse: bool);
+}
+
+macro_rules! simd_element {
+    ($t:ty, $v:ty, $lanes:expr) => {
+        impl sealed::Sealed for $t {}
+
+        impl SimdElement for $t {
+            fn __half_clean(lo: &mut [$t], hi: &mut [$t], reverse: bool) {
+                let mut lo = lo.chunks_exact_mut($lanes);
+                let mut hi = hi.chunks_exact_mut($lanes);
+                for (a, b) in (&mut lo).zip(&mut hi) {
+                    let x = <$v>::new(a.try_into().unwrap());
+                    let y = <$v>::new(b.try_into().unwrap());
+                    let swap = if reverse { x.cmp_lt(y) } else { x.cmp_gt(y) };
+                    a.copy_from_slice(&swap.blend(y, x).to_array());
+                    b.copy_from_slice(&swap.blend(x, y).to_array());
+                }
+                let (lo, hi) = (lo.into_remainder(), hi.into_remainder());
+                for (num1, num2) in lo.iter_mut().zip(hi.iter_mut()) {
+                    if bitonic_serial::__should_swap(__cmp(num1, num2), reverse) {
+                        core::mem::swap(num1, num2);
+                    }
+                }
+            }
+        }
+    };
+}
+
+simd_element!(f32, f32x8, 8);
+simd_element!(f64, f64x4, 4);
+
+/// Sorts the given vector of floats in ascending order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_simd::bitonic_sort;
+///
+/// let mut nums: Vec<f64> = (0..100).map(|x| ((x * 37) % 100) as f64).collect();
+/// bitonic_sort(&mut nums);
+/// assert!(nums.windows(2).all(|w| w[0] <= w[1]));
+/// ```
+pub fn bitonic_sort<T>(nums: &mut Vec<T>)
+where
+    T: SimdElement,
+{
+    __bitonic_sort(&mut nums[..], false);
+}
+
+fn __bitonic_merge<T: SimdElement>(nums: &mut [T], reverse: bool) {
+    let len = nums.len();
+    let stride = bitonic_serial::__stride(len);
+    if stride < SIMD_MIN_STRIDE {
+        bitonic_serial::__bitonic_merge(nums, reverse, &__cmp);
+        return;
+    }
+    let (lo, hi) = nums.split_at_mut(stride);
+    let pairs = hi.len();
+    T::__half_clean(&mut lo[..pairs], hi, reverse);
+    __bitonic_merge(lo, reverse);
+    __bitonic_merge(hi, reverse);
+}
+
+fn __bitonic_sort<T: SimdElement>(nums: &mut [T], reverse: bool) {
+    let len = nums.len();
+    if len < SIMD_MIN_STRIDE * 2 {
+        bitonic_serial::__bitonic_sort(nums, reverse, &__cmp);
+        return;
+    }
+    __bitonic_sort(&mut nums[..len / 2], !reverse);
+    __bitonic_sort(&mut nums[len / 2..], reverse);
+    __bitonic_merge(nums, reverse);
+}
+
+fn __cmp<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_bitonic_sort() {
+        let mut nums = vec![4.0f32, 2.0, 7.0, 1.0, 5.0, 3.0, 6.0];
+        bitonic_sort(&mut nums);
+        assert_eq!(nums, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn test_bitonic_sort_matches_scalar() {
+        for len in [0, 1, 63, 64, 100, 1000, 4099] {
+            let nums: Vec<f32> = gen_few_unique(len, 11)
+                .into_iter()
+                .map(|x| match x {
+                    0 => f32::NAN,
+                    1 => -0.0,
+                    2 => 0.0,
+                    n => n as f32 - 6.0,
+                })
+                .collect();
+            let mut expected = nums.clone();
+            bitonic_serial::bitonic_sort(&mut expected);
+            let mut sorted = nums.clone();
+            bitonic_sort(&mut sorted);
+            let bits = |v: &[f32]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+            assert!(
+                bits(&sorted) == bits(&expected),
+                "f32 differs for len = {}",
+                len
+            );
+
+            let nums: Vec<f64> = nums.iter().map(|&x| x as f64).collect();
+            let mut expected = nums.clone();
+            bitonic_serial::bitonic_sort(&mut expected);
+            let mut sorted = nums.clone();
+            bitonic_sort(&mut sorted);
+            let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+            assert!(
+                bits(&sorted) == bits(&expected),
+                "f64 differs for len = {}",
+                len
+            );
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! A configuration object for the parallel sorts.
+//!
+//! The free functions of [`bitonic_parallel`] and [`parallel_sort`] each take the
+//! options they need as arguments. [`SortConfig`] collects them in one place instead, and
+//! [`SortConfig::build`] turns it into a [`Sorter`] that keeps its threads, and the
+//! scratch buffer of the merge sort, across calls.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::config::SortConfig;
+//!
+//! let mut sorter = SortConfig::new().parallel(8).reverse(true).min_parallel_len(4096).build();
+//! let mut nums = vec![4, 2, 7, 1, 5];
+//! sorter.sort(&mut nums);
+//! assert_eq!(nums, vec![7, 5, 4, 2, 1]);
+//! ```
+//!
+//! [`bitonic_parallel`]: crate::bitonic_parallel
+//! [`parallel_sort`]: crate::parallel_sort
+use crate::bitonic_parallel;
+use crate::parallel_sort;
+use crate::pool::ThreadPool;
+use crate::topology;
+use crate::validate;
+
+/// The algorithms a [`Sorter`] can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    /// The parallel bitonic network of [`bitonic_parallel::bitonic_sort`].
+    #[default]
+    Bitonic,
+    /// The network's merges over parts sorted by the standard library, see
+    /// [`bitonic_parallel::bitonic_sort_hybrid`].
+    Hybrid,
+    /// The parallel merge sort of [`parallel_sort::parallel_sort`], which needs a scratch
+    /// buffer as long as the input and panics on incomparable elements.
+    Merge,
+}
+
+/// The options of a sort, set one at a time and turned into a [`Sorter`] by
+/// [`build`](SortConfig::build).
+///
+/// The defaults are those of the free functions: a serial [`Algorithm::Bitonic`] sort
+/// in ascending order without a `min_parallel_len`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortConfig {
+    parallel: usize,
+    reverse: bool,
+    min_parallel_len: usize,
+    algorithm: Algorithm,
+}
+
+impl SortConfig {
+    /// Creates a configuration with the default options.
+    pub fn new() -> SortConfig {
+        SortConfig::default()
+    }
+
+    /// Sets the number of parts the work is split into, as the `parallel` argument of
+    /// the free functions.
+    pub fn parallel(mut self, parallel: usize) -> SortConfig {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Sets whether to sort in descending order.
+    pub fn reverse(mut self, reverse: bool) -> SortConfig {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Sets the length below which [`Algorithm::Bitonic`] stops spreading work across
+    /// threads, see [`bitonic_parallel::bitonic_sort_with_min_len`]. The other
+    /// algorithms ignore it.
+    pub fn min_parallel_len(mut self, min_parallel_len: usize) -> SortConfig {
+        self.min_parallel_len = min_parallel_len;
+        self
+    }
+
+    /// Sets the algorithm to run.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> SortConfig {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Starts the threads for the configured parallelism and returns the sorter.
+    ///
+    /// The calling thread works on one of the parts itself, so the pool gets a worker
+    /// for each of the others, but no more than [`topology::logical_cores`] threads in
+    /// total: parts beyond that wait in the pool's queue. Each worker is an OS thread
+    /// with a stack of its own that lives as long as the sorter, so a large `parallel`
+    /// costs at most one such thread per core.
+    pub fn build<T>(self) -> Sorter<T> {
+        let parts = match self.algorithm {
+            Algorithm::Bitonic | Algorithm::Hybrid => crate::normalize_parallel(self.parallel),
+            Algorithm::Merge => self.parallel.max(1),
+        };
+        let pool = ThreadPool::new(parts.min(topology::logical_cores()) - 1);
+        Sorter {
+            config: self,
+            pool,
+            scratch: Vec::new(),
+        }
+    }
+}
+
+/// Sorts slices of `T` as configured by a [`SortConfig`].
+///
+/// The threads live as long as the sorter, and [`Algorithm::Merge`] keeps its scratch
+/// buffer between calls, growing it to the longest input sorted so far.
+pub struct Sorter<T> {
+    config: SortConfig,
+    pool: ThreadPool,
+    scratch: Vec<T>,
+}
+
+impl<T> Sorter<T>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    /// Sorts `nums` in place.
+    pub fn sort(&mut self, nums: &mut [T]) {
+        let SortConfig {
+            parallel,
+            reverse,
+            min_parallel_len,
+            algorithm,
+        } = self.config;
+        match algorithm {
+            Algorithm::Bitonic | Algorithm::Hybrid => {
+                let cmp = bitonic_parallel::__cmp;
+                let parallel = bitonic_parallel::__effective_parallel(parallel, nums.len());
+                if algorithm == Algorithm::Hybrid {
+                    bitonic_parallel::__hybrid_sort(nums, reverse, &cmp, parallel, &self.pool);
+                } else {
+                    bitonic_parallel::__bitonic_sort(
+                        nums,
+                        reverse,
+                        &cmp,
+                        parallel,
+                        min_parallel_len,
+                        &self.pool,
+                        None,
+                    );
+                }
+            }
+            Algorithm::Merge => {
+                let cmp = |x: &T, y: &T| {
+                    let ord = x.partial_cmp(y).expect("elements are not comparable");
+                    if reverse {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                };
+                self.scratch.clear();
+                self.scratch.extend_from_slice(nums);
+                parallel_sort::__sort_runs(
+                    nums,
+                    &mut self.scratch,
+                    parallel,
+                    &cmp,
+                    &self.pool,
+                    None,
+                    parallel_sort::RunSort::Unstable,
+                );
+                // unlike the network's, this comparator is a total order
+                validate::__debug_assert_sorted(nums, false, &cmp);
+            }
+        }
+    }
+
+    /// Returns the configuration the sorter was built from.
+    pub fn config(&self) -> &SortConfig {
+        &self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_sorter_algorithms() {
+        for algorithm in [Algorithm::Bitonic, Algorithm::Hybrid, Algorithm::Merge] {
+            for reverse in [false, true] {
+                let mut sorter = SortConfig::new()
+                    .parallel(4)
+                    .reverse(reverse)
+                    .algorithm(algorithm)
+                    .build();
+                // the same sorter, and its scratch, is reused for every length
+                for len in [0, 1, 7, 513, 1000, 3] {
+                    let mut nums = gen_few_unique(len, 100);
+                    let mut expected = nums.clone();
+                    expected.sort();
+                    if reverse {
+                        expected.reverse();
+                    }
+                    sorter.sort(&mut nums);
+                    assert!(
+                        nums == expected,
+                        "unsorted output for {:?}, reverse = {}, len = {}",
+                        algorithm,
+                        reverse,
+                        len
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = SortConfig::new();
+        assert_eq!(config, SortConfig::default());
+        let sorter: Sorter<u32> = config.min_parallel_len(4096).build();
+        assert_eq!(sorter.config().min_parallel_len, 4096);
+        assert_eq!(sorter.config().algorithm, Algorithm::Bitonic);
+    }
+
+    #[test]
+    fn test_sorter_threads_bounded_by_cores() {
+        for algorithm in [Algorithm::Bitonic, Algorithm::Hybrid, Algorithm::Merge] {
+            let mut sorter = SortConfig::new()
+                .parallel(usize::MAX)
+                .algorithm(algorithm)
+                .build();
+            assert!(sorter.pool.threads() < topology::logical_cores());
+            let mut nums = vec![4, 2, 7, 1, 5];
+            sorter.sort(&mut nums);
+            assert_eq!(
+                nums,
+                vec![1, 2, 4, 5, 7],
+                "unsorted output for {:?}",
+                algorithm
+            );
+        }
+    }
+}
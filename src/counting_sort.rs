@@ -0,0 +1,174 @@
+//! This module contains counting sorts for inputs with few distinct values.
+//!
+//! Booleans, bytes and small enums take so few values that counting how often each one
+//! occurs and writing them back in order is far cheaper than any comparison network:
+//! one pass to count, one to write, O(n) in total. Prefer these sorts whenever the
+//! domain is that small; for anything else use the networks, whose cost does not depend
+//! on the values.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::counting_sort::counting_sort_u8;
+//!
+//! let mut bytes = *b"counting";
+//! counting_sort_u8(&mut bytes);
+//! assert_eq!(&bytes, b"cginnotu");
+//! ```
+use alloc::vec::Vec;
+
+/// The most distinct values [`sort_small_domain`] counts before it falls back to a
+/// comparison sort, enough for every value of a one-byte type.
+pub const SMALL_DOMAIN_LIMIT: usize = 256;
+
+/// Sorts bytes in ascending order by counting them.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::counting_sort::counting_sort_u8;
+///
+/// let mut nums = [4, 2, 7, 2, 0, 255];
+/// counting_sort_u8(&mut nums);
+/// assert_eq!(nums, [0, 2, 2, 4, 7, 255]);
+/// ```
+pub fn counting_sort_u8(nums: &mut [u8]) {
+    let mut counts = [0usize; 256];
+    for &x in nums.iter() {
+        counts[x as usize] += 1;
+    }
+    let mut rest = nums;
+    for (value, &count) in counts.iter().enumerate() {
+        let (run, tail) = core::mem::take(&mut rest).split_at_mut(count);
+        run.fill(value as u8);
+        rest = tail;
+    }
+}
+
+/// Sorts booleans, `false` before `true`, by counting them.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::counting_sort::counting_sort_bool;
+///
+/// let mut flags = [true, false, true, false];
+/// counting_sort_bool(&mut flags);
+/// assert_eq!(flags, [false, false, true, true]);
+/// ```
+pub fn counting_sort_bool(nums: &mut [bool]) {
+    let falses = nums.iter().filter(|&&x| !x).count();
+    let (lo, hi) = nums.split_at_mut(falses);
+    lo.fill(false);
+    hi.fill(true);
+}
+
+/// Sorts a slice with few distinct values in ascending order by counting them.
+///
+/// The distinct values are collected in a sorted table of at most
+/// [`SMALL_DOMAIN_LIMIT`] entries, each element is counted under its entry, and the
+/// elements are then moved to their runs through a buffer of `n` elements. This takes
+/// O(n log k) comparisons for `k` distinct values, plus O(k^2) moves to build the table,
+/// and it is stable: equal elements keep their input order, so it also works for records
+/// that compare equal without being identical. A type of one byte, such as `bool`, `u8`,
+/// `i8` or a small enum, cannot have more values than the table holds. Other inputs with
+/// more distinct values are sorted with `sort_unstable` instead, which is then unstable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::counting_sort::sort_small_domain;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// enum Level {
+///     Low,
+///     Mid,
+///     High,
+/// }
+///
+/// let mut levels = [Level::High, Level::Low, Level::Mid, Level::Low];
+/// sort_small_domain(&mut levels);
+/// assert_eq!(levels, [Level::Low, Level::Low, Level::Mid, Level::High]);
+/// ```
+pub fn sort_small_domain<T>(nums: &mut [T])
+where
+    T: Ord + Copy,
+{
+    // the distinct values, sorted
+    let mut values: Vec<T> = Vec::new();
+    for x in nums.iter() {
+        if let Err(entry) = values.binary_search(x) {
+            if values.len() == SMALL_DOMAIN_LIMIT {
+                nums.sort_unstable();
+                return;
+            }
+            values.insert(entry, *x);
+        }
+    }
+    let entry = |x: &T| values.binary_search(x).unwrap_or_else(|entry| entry);
+    let mut starts = [0usize; SMALL_DOMAIN_LIMIT];
+    for x in nums.iter() {
+        starts[entry(x)] += 1;
+    }
+    let mut start = 0;
+    for count in starts.iter_mut() {
+        (*count, start) = (start, start + *count);
+    }
+    let mut sorted = nums.to_vec();
+    for x in nums.iter() {
+        let entry = entry(x);
+        sorted[starts[entry]] = *x;
+        starts[entry] += 1;
+    }
+    nums.copy_from_slice(&sorted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{gen_few_unique, gen_tagged, gen_uniform, SEED};
+
+    #[test]
+    fn test_counting_sort_u8() {
+        let mut nums: Vec<u8> = gen_uniform(1000, SEED)
+            .into_iter()
+            .map(|x| (x >> 24) as u8)
+            .collect();
+        let mut expected = nums.clone();
+        expected.sort();
+        counting_sort_u8(&mut nums);
+        assert_eq!(nums, expected);
+        counting_sort_u8(&mut []);
+    }
+
+    #[test]
+    fn test_counting_sort_bool() {
+        let mut flags: Vec<bool> = (0..100u32).map(|x| x % 3 == 0).collect();
+        counting_sort_bool(&mut flags);
+        assert_eq!(flags.iter().filter(|&&x| !x).count(), 66);
+        assert!(flags.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_sort_small_domain_is_stable() {
+        let mut nums = gen_tagged(500, 7);
+        let mut expected = nums.clone();
+        expected.sort_by_key(|x| x.0);
+        sort_small_domain(&mut nums);
+        assert!(nums
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| a.0 == b.0 && a.1 == b.1));
+    }
+
+    #[test]
+    fn test_sort_small_domain_falls_back() {
+        for (len, modulus) in [(0, 1), (1000, 256), (1000, 257), (3000, 3000)] {
+            let mut nums = gen_few_unique(len, modulus);
+            let mut expected = nums.clone();
+            expected.sort();
+            sort_small_domain(&mut nums);
+            assert!(nums == expected, "unsorted output for {} values", modulus);
+        }
+    }
+}
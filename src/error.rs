@@ -0,0 +1,28 @@
+//! Error types returned by the fallible sort functions.
+use core::error::Error;
+use core::fmt;
+
+/// The reasons a fallible sort can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortError {
+    /// The element at `index` of the input could not be compared, e.g. a `NaN` float.
+    Incomparable { index: usize },
+    /// The sort was cancelled before it finished; the input is only partially sorted.
+    Cancelled,
+    /// The sort ran out of its time budget; the input is only partially sorted.
+    TimedOut,
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortError::Incomparable { index } => {
+                write!(f, "element at index {} is not comparable", index)
+            }
+            SortError::Cancelled => write!(f, "the sort was cancelled"),
+            SortError::TimedOut => write!(f, "the sort ran out of time"),
+        }
+    }
+}
+
+impl Error for SortError {}
@@ -0,0 +1,78 @@
+//! Extension traits that make the parallel bitonic sort available as methods.
+//!
+//! [`BitonicSortExt`] sorts slices and vectors in place, [`BitonicIteratorExt`] collects
+//! any iterator into a sorted vector. Both delegate to [`bitonic_parallel`].
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::ext::{BitonicIteratorExt, BitonicSortExt};
+//!
+//! let mut nums = vec![4, 2, 7, 1, 5];
+//! nums.bitonic_sort_in_place(4);
+//! assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+//!
+//! let squares = (1..=5).rev().map(|x| x * x).bitonic_sorted(4);
+//! assert_eq!(squares, vec![1, 4, 9, 16, 25]);
+//! ```
+//!
+//! [`bitonic_parallel`]: crate::bitonic_parallel
+use crate::bitonic_parallel;
+
+/// Sorts a slice in place, see [`bitonic_parallel::bitonic_sort_slice`].
+///
+/// It is implemented for `[T]`, so it applies to `Vec<T>`, arrays and `&mut [T]` alike.
+pub trait BitonicSortExt {
+    /// Sorts `self` in ascending order, splitting the work into `parallel` parts.
+    fn bitonic_sort_in_place(&mut self, parallel: usize);
+}
+
+impl<T> BitonicSortExt for [T]
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    fn bitonic_sort_in_place(&mut self, parallel: usize) {
+        bitonic_parallel::bitonic_sort_slice(self, parallel);
+    }
+}
+
+/// Collects an iterator into a sorted vector, see [`bitonic_parallel::into_sorted_vec`].
+///
+/// This is a separate trait from [`BitonicSortExt`]: a blanket implementation for every
+/// iterator cannot coexist with one for `Vec<T>`.
+pub trait BitonicIteratorExt: Iterator {
+    /// Collects the items and sorts them in ascending order, splitting the work into
+    /// `parallel` parts.
+    fn bitonic_sorted(self, parallel: usize) -> Vec<Self::Item>
+    where
+        Self: Sized,
+        Self::Item: PartialOrd + Copy + Send + Sync,
+    {
+        bitonic_parallel::into_sorted_vec(self, parallel)
+    }
+}
+
+impl<I: Iterator> BitonicIteratorExt for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitonic_sort_in_place() {
+        let mut nums = vec![4, 2, 7, 1, 5, 3, 6];
+        nums.bitonic_sort_in_place(2);
+        assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+
+        let mut nums = [4, 2, 7, 1, 5, 3, 6];
+        nums[2..].bitonic_sort_in_place(2);
+        assert_eq!(nums, [4, 2, 1, 3, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_bitonic_sorted() {
+        let sorted = [3.5, -1.0, 2.0].iter().copied().bitonic_sorted(4);
+        assert_eq!(sorted, vec![-1.0, 2.0, 3.5]);
+        assert!(std::iter::empty::<u8>().bitonic_sorted(4).is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+//! A bitonic sort of `f32` values on the GPU, through `wgpu`.
+//!
+//! The sorting network is the same as on the CPU, but every compare-exchange of a pass
+//! runs on its own GPU thread. The values are uploaded as `u32` keys that order like
+//! [`f32::total_cmp`], padded with the largest key up to the next power of two, sorted
+//! by one compute dispatch per pass of the network, and downloaded again.
+//!
+//! Needs the `gpu` feature. Without a usable adapter, [`gpu_bitonic_sort`] sorts on the
+//! CPU instead, and [`try_gpu_bitonic_sort`] reports why.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::gpu::gpu_bitonic_sort;
+//!
+//! let mut nums = vec![2.5, -1.0, 3.0, 0.0, 7.5];
+//! gpu_bitonic_sort(&mut nums);
+//! assert_eq!(nums, vec![-1.0, 0.0, 2.5, 3.0, 7.5]);
+//! ```
+use crate::bitonic_serial;
+use std::fmt;
+use std::sync::mpsc;
+use wgpu::util::DeviceExt;
+
+// One thread per element; a thread whose partner has a lower index does nothing.
+const SHADER: &str = r#"
+struct Params {
+    size: u32,
+    stride: u32,
+    len: u32,
+    row: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> keys: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.y * params.row + id.x;
+    let l = i ^ params.stride;
+    if (i >= params.len || l <= i) {
+        return;
+    }
+    let a = keys[i];
+    let b = keys[l];
+    let ascending = (i & params.size) == 0u;
+    if ((ascending && a > b) || (!ascending && a < b)) {
+        keys[i] = b;
+        keys[l] = a;
+    }
+}
+"#;
+
+const WORKGROUP_LEN: u32 = 256;
+
+// The most workgroups a dispatch may have along one dimension.
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// The reasons the GPU sort can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuError {
+    /// No GPU adapter is available.
+    NoAdapter,
+    /// The adapter could not open a device.
+    NoDevice,
+    /// The padded input does not fit into one storage buffer of the device.
+    TooLarge,
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter => write!(f, "no GPU adapter is available"),
+            GpuError::NoDevice => write!(f, "the GPU adapter could not open a device"),
+            GpuError::TooLarge => write!(f, "the input does not fit into a GPU buffer"),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+/// Sorts the given vector on the GPU, in the order of [`f32::total_cmp`].
+///
+/// Every value has a defined place: negative `NaN`s first, then the numbers with `-0.0`
+/// before `0.0`, then positive `NaN`s. The vector keeps its length and capacity; only
+/// the GPU buffer is padded. If no GPU can be used, the vector is sorted by the serial
+/// network on the CPU, in the same order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::gpu::gpu_bitonic_sort;
+///
+/// let mut nums = vec![0.0, f32::NAN, -0.0, -2.0];
+/// gpu_bitonic_sort(&mut nums);
+/// assert_eq!(nums[..3], [-2.0, -0.0, 0.0]);
+/// assert!(nums[3].is_nan());
+/// ```
+pub fn gpu_bitonic_sort(nums: &mut Vec<f32>) {
+    if try_gpu_bitonic_sort(nums).is_err() {
+        bitonic_serial::bitonic_sort_by(nums, f32::total_cmp);
+    }
+}
+
+/// Sorts the given slice on the GPU like [`gpu_bitonic_sort`], returning an error
+/// instead of sorting on the CPU. The slice is unchanged on error.
+pub fn try_gpu_bitonic_sort(nums: &mut [f32]) -> Result<(), GpuError> {
+    if nums.len() <= 1 {
+        return Ok(());
+    }
+    let padded_len = nums
+        .len()
+        .checked_next_power_of_two()
+        .ok_or(GpuError::TooLarge)?;
+    let len = u32::try_from(padded_len).map_err(|_| GpuError::TooLarge)?;
+    let (device, queue) = pollster::block_on(__device())?;
+    let bytes = u64::from(len) * 4;
+    if bytes > u64::from(device.limits().max_storage_buffer_binding_size) {
+        return Err(GpuError::TooLarge);
+    }
+
+    let mut keys: Vec<u8> = Vec::with_capacity(padded_len * 4);
+    keys.extend(nums.iter().flat_map(|x| __key(*x).to_ne_bytes()));
+    keys.resize(padded_len * 4, u8::MAX);
+    let storage = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("bitonic keys"),
+        contents: &keys,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("bitonic readback"),
+        size: bytes,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("bitonic pass"),
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("bitonic pass"),
+        layout: None,
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    // spread the workgroups over two dimensions once one is not enough
+    let groups = len.div_ceil(WORKGROUP_LEN);
+    let (columns, rows) = (groups.min(MAX_WORKGROUPS), groups.div_ceil(MAX_WORKGROUPS));
+    let mut encoder = device.create_command_encoder(&Default::default());
+    let mut size = 2;
+    while size <= len {
+        let mut stride = size / 2;
+        while stride > 0 {
+            let params: Vec<u8> = [size, stride, len, columns * WORKGROUP_LEN]
+                .iter()
+                .flat_map(|x| x.to_ne_bytes())
+                .collect();
+            let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("bitonic params"),
+                contents: &params,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: storage.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: uniform.as_entire_binding(),
+                    },
+                ],
+            });
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(columns, rows, 1);
+            drop(pass);
+            stride /= 2;
+        }
+        size *= 2;
+    }
+    encoder.copy_buffer_to_buffer(&storage, 0, &staging, 0, bytes);
+    queue.submit([encoder.finish()]);
+
+    let slice = staging.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .ok()
+        .and_then(Result::ok)
+        .ok_or(GpuError::NoDevice)?;
+    let sorted = slice.get_mapped_range();
+    // the padding keys are the largest, so the first `nums.len()` keys are the input's
+    for (num, key) in nums.iter_mut().zip(sorted.chunks_exact(4)) {
+        *num = __value(u32::from_ne_bytes(key.try_into().unwrap()));
+    }
+    Ok(())
+}
+
+async fn __device() -> Result<(wgpu::Device, wgpu::Queue), GpuError> {
+    let instance = wgpu::Instance::new(&Default::default());
+    let adapter = instance
+        .request_adapter(&Default::default())
+        .await
+        .ok_or(GpuError::NoAdapter)?;
+    adapter
+        .request_device(&Default::default(), None)
+        .await
+        .map_err(|_| GpuError::NoDevice)
+}
+
+// Maps `x` to a key whose unsigned order is the order of `f32::total_cmp`: negative
+// values have all bits flipped, so that larger magnitudes come first, and positive ones
+// only the sign bit, so that they follow the negative ones.
+fn __key(x: f32) -> u32 {
+    let bits = x.to_bits();
+    if bits >> 31 == 1 {
+        !bits
+    } else {
+        bits | 1 << 31
+    }
+}
+
+fn __value(key: u32) -> f32 {
+    f32::from_bits(if key >> 31 == 1 {
+        key & !(1 << 31)
+    } else {
+        !key
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_keys_order_like_total_cmp() {
+        let mut nums = vec![
+            f32::NAN,
+            -f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            0.0,
+            -0.0,
+            1.5,
+            -1.5,
+            f32::MIN_POSITIVE,
+        ];
+        for &x in &nums {
+            assert_eq!(__value(__key(x)).to_bits(), x.to_bits());
+        }
+        let mut keys: Vec<u32> = nums.iter().map(|&x| __key(x)).collect();
+        keys.sort();
+        nums.sort_by(f32::total_cmp);
+        let sorted: Vec<u32> = nums.iter().map(|&x| __key(x)).collect();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn test_gpu_bitonic_sort() {
+        for len in [0, 1, 2, 7, 1000, 4096, 70_000] {
+            let mut nums: Vec<f32> = gen_few_unique(len, 1000)
+                .into_iter()
+                .map(|x| x as f32 - 500.0)
+                .collect();
+            if len > 2 {
+                nums[len / 2] = f32::NAN;
+                nums[len / 3] = -0.0;
+            }
+            let mut expected = nums.clone();
+            expected.sort_by(f32::total_cmp);
+            let bits = |nums: &[f32]| nums.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+
+            // without a GPU, the input must come back untouched
+            let mut on_gpu = nums.clone();
+            match try_gpu_bitonic_sort(&mut on_gpu) {
+                Ok(()) => assert!(bits(&on_gpu) == bits(&expected), "len = {}", len),
+                Err(_) => assert!(bits(&on_gpu) == bits(&nums)),
+            }
+            gpu_bitonic_sort(&mut nums);
+            assert!(
+                bits(&nums) == bits(&expected),
+                "unsorted output for len = {}",
+                len
+            );
+        }
+    }
+}
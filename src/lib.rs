@@ -1,8 +1,137 @@
 /*!
 This module contains the implementation of the bitonic sort algorithm.
 It provides both serial and parallel versions of the algorithm.
+
+Everything that spawns threads needs the default `std` feature. Without it the crate is
+`no_std` and only needs `alloc`, keeping `bitonic_serial`, `counting_sort`, `network`,
+`odd_even_merge_sort`, `search`, `validate` and `error`.
+
+Every sort accepts any length a slice can have, up to the `usize::MAX` elements of a
+slice of zero-sized values. No index computation can overflow `usize` on the way, also
+on 32-bit targets, and all splitting goes through `split_at_mut` rather than unchecked
+pointer arithmetic.
 */
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod argsort;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "ndarray")]
+pub mod bitonic_ndarray;
+#[cfg(feature = "std")]
 pub mod bitonic_parallel;
+#[cfg(feature = "rayon")]
+pub mod bitonic_rayon;
 pub mod bitonic_serial;
-
+#[cfg(feature = "simd")]
+pub mod bitonic_simd;
+#[cfg(feature = "std")]
+pub mod config;
+pub mod counting_sort;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod ext;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod network;
+pub mod odd_even_merge_sort;
+#[cfg(feature = "std")]
 pub mod parallel_sort;
+#[cfg(feature = "std")]
+pub mod pool;
+pub mod search;
+#[cfg(feature = "std")]
+pub mod select;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "std")]
+pub mod topology;
+pub mod validate;
+
+#[cfg(feature = "std")]
+use std::thread;
+
+// The number of parts the parallel bitonic sort splits its input into. Zero and one both
+// mean serial, on the calling thread; anything else is rounded up to a power of two,
+// which the halving recursion of the network needs. Counts beyond the largest power of
+// two are capped at it instead of overflowing.
+#[cfg(feature = "std")]
+pub(crate) fn normalize_parallel(parallel: usize) -> usize {
+    match parallel {
+        0 | 1 => 1,
+        parallel => parallel
+            .checked_next_power_of_two()
+            .unwrap_or(1 << (usize::BITS - 1)),
+    }
+}
+
+// Picks a thread count for the `*_auto` sorts: the largest power of two that neither
+// exceeds the available cores nor leaves a thread with fewer than two elements.
+// Returns `None` when the core count is unknown, in which case callers sort serially.
+#[cfg(feature = "std")]
+pub(crate) fn auto_parallel(len: usize) -> Option<usize> {
+    let cores = thread::available_parallelism().ok()?.get();
+    let limit = cores.min(len / 2).max(1);
+    Some(1 << limit.ilog2())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::validate::is_sorted;
+    use crate::{bitonic_parallel, bitonic_serial, parallel_sort};
+    use proptest::prelude::*;
+
+    proptest! {
+        // Random lengths and thread counts, against the standard library's sort. The
+        // lengths reach past the powers of two where the splitting of the parallel sorts
+        // changes.
+        #[test]
+        fn test_sorts_match_std(
+            nums in prop::collection::vec(any::<i64>(), 0..3000),
+            parallel in 0usize..20,
+        ) {
+            let mut expected = nums.clone();
+            expected.sort();
+
+            let mut serial = nums.clone();
+            bitonic_serial::bitonic_sort(&mut serial);
+            prop_assert!(is_sorted(&serial));
+            prop_assert_eq!(&serial, &expected);
+
+            let mut bitonic = nums.clone();
+            bitonic_parallel::bitonic_sort(&mut bitonic, parallel);
+            prop_assert!(is_sorted(&bitonic));
+            prop_assert_eq!(&bitonic, &expected);
+
+            let mut merged = nums;
+            parallel_sort::parallel_sort(&mut merged, parallel);
+            prop_assert!(is_sorted(&merged));
+            prop_assert_eq!(&merged, &expected);
+        }
+
+        // Few distinct values, so that the inputs are full of duplicate runs.
+        #[test]
+        fn test_sorts_match_std_with_duplicates(
+            nums in prop::collection::vec(-3i64..3, 0..600),
+            parallel in 0usize..9,
+        ) {
+            let mut expected = nums.clone();
+            expected.sort();
+
+            let mut serial = nums.clone();
+            bitonic_serial::bitonic_sort(&mut serial);
+            prop_assert_eq!(&serial, &expected);
+
+            let mut bitonic = nums.clone();
+            bitonic_parallel::bitonic_sort(&mut bitonic, parallel);
+            prop_assert_eq!(&bitonic, &expected);
+
+            let mut merged = nums;
+            parallel_sort::parallel_sort(&mut merged, parallel);
+            prop_assert_eq!(&merged, &expected);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! This module runs sorting networks given as a schedule of compare-exchanges.
+//!
+//! A [`NetworkSchedule`] lists the `(i, j, reverse)` steps of a network for a given length,
+//! in execution order, and [`network_sort`] executes any schedule over a slice. The
+//! networks of this crate are available as [`BitonicSchedule`] and
+//! [`OddEvenMergeSchedule`]; other networks only need to implement the trait.
+//!
+//! The sorts of the other modules do not go through a schedule: they run their networks
+//! directly, without materializing the steps.
+//!
+//! # Examples
+//!
+//! An odd-even transposition network, which compares neighbours in `len` alternating
+//! rounds:
+//!
+//! ```
+//! use bitonic_sort::network::{network_sort, NetworkSchedule};
+//!
+//! struct OddEvenTransposition;
+//!
+//! impl NetworkSchedule for OddEvenTransposition {
+//!     fn steps(&self, len: usize) -> Vec<(usize, usize, bool)> {
+//!         (0..len)
+//!             .flat_map(|round| (round % 2..len.saturating_sub(1)).step_by(2))
+//!             .map(|i| (i, i + 1, false))
+//!             .collect()
+//!     }
+//! }
+//!
+//! let mut nums = [4, 2, 7, 1, 5];
+//! network_sort(&mut nums, &OddEvenTransposition);
+//! assert_eq!(nums, [1, 2, 4, 5, 7]);
+//! ```
+
+use crate::bitonic_serial::{self, __should_swap};
+use crate::odd_even_merge_sort;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// The compare-exchange steps of a sorting network.
+pub trait NetworkSchedule {
+    /// Returns the steps that sort `len` elements, in execution order.
+    ///
+    /// Each `(i, j, reverse)` step compares the elements at `i` and `j` and swaps them if
+    /// the one at `i` is greater, or less when `reverse` is set. Both indices must be
+    /// below `len`.
+    fn steps(&self, len: usize) -> Vec<(usize, usize, bool)>;
+}
+
+/// The bitonic network of [`bitonic_serial`], see
+/// [`bitonic_network`](bitonic_serial::bitonic_network).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BitonicSchedule;
+
+impl NetworkSchedule for BitonicSchedule {
+    fn steps(&self, len: usize) -> Vec<(usize, usize, bool)> {
+        bitonic_serial::bitonic_network(len)
+    }
+}
+
+/// Batcher's odd-even merge network, see
+/// [`odd_even_network`](odd_even_merge_sort::odd_even_network).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OddEvenMergeSchedule;
+
+impl NetworkSchedule for OddEvenMergeSchedule {
+    fn steps(&self, len: usize) -> Vec<(usize, usize, bool)> {
+        odd_even_merge_sort::odd_even_network(len)
+            .into_iter()
+            .map(|(i, j)| (i, j, false))
+            .collect()
+    }
+}
+
+/// Sorts the given slice by executing the steps of `schedule` for its length.
+///
+/// Equal and incomparable elements are never swapped. Whether the result is sorted is
+/// up to the schedule.
+///
+/// # Panics
+///
+/// Panics if a step refers to an index past the end of the slice.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::network::{network_sort, BitonicSchedule};
+///
+/// let mut nums = [4, 2, 7, 1, 5];
+/// network_sort(&mut nums, &BitonicSchedule);
+/// assert_eq!(nums, [1, 2, 4, 5, 7]);
+/// ```
+pub fn network_sort<T, S>(nums: &mut [T], schedule: &S)
+where
+    T: PartialOrd,
+    S: NetworkSchedule + ?Sized,
+{
+    network_sort_by(nums, schedule, |a, b| {
+        a.partial_cmp(b).unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the given slice like [`network_sort`], with a custom comparator closure.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::network::{network_sort_by, OddEvenMergeSchedule};
+///
+/// let mut nums = [1, 3, 2];
+/// network_sort_by(&mut nums, &OddEvenMergeSchedule, |a, b| b.cmp(a));
+/// assert_eq!(nums, [3, 2, 1]);
+/// ```
+pub fn network_sort_by<T, S, F>(nums: &mut [T], schedule: &S, cmp: F)
+where
+    S: NetworkSchedule + ?Sized,
+    F: Fn(&T, &T) -> Ordering,
+{
+    for (i, j, reverse) in schedule.steps(nums.len()) {
+        if __should_swap(cmp(&nums[i], &nums[j]), reverse) {
+            nums.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+    use alloc::boxed::Box;
+
+    #[test]
+    fn test_schedules_sort() {
+        let schedules: [Box<dyn NetworkSchedule>; 2] =
+            [Box::new(BitonicSchedule), Box::new(OddEvenMergeSchedule)];
+        for schedule in &schedules {
+            for len in 0..70 {
+                let nums = gen_few_unique(len, 50);
+                let mut expected = nums.clone();
+                expected.sort();
+                let mut sorted = nums;
+                network_sort(&mut sorted, &**schedule);
+                assert!(sorted == expected, "unsorted output for len = {}", len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_schedule_matches_sort() {
+        for len in 0..70 {
+            let nums: Vec<f64> = gen_few_unique(len, 50)
+                .into_iter()
+                .enumerate()
+                .map(|(i, x)| match i % 7 {
+                    0 => f64::NAN,
+                    _ => x as f64,
+                })
+                .collect();
+            let mut scheduled = nums.clone();
+            network_sort(&mut scheduled, &BitonicSchedule);
+            let mut sorted = nums;
+            bitonic_serial::bitonic_sort_by(&mut sorted, |a, b| {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            });
+            assert!(
+                scheduled
+                    .iter()
+                    .zip(&sorted)
+                    .all(|(a, b)| a.total_cmp(b).is_eq()),
+                "different output for len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_step_out_of_bounds() {
+        struct Broken;
+        impl NetworkSchedule for Broken {
+            fn steps(&self, len: usize) -> Vec<(usize, usize, bool)> {
+                alloc::vec![(0, len, false)]
+            }
+        }
+        network_sort(&mut [2, 1], &Broken);
+    }
+}
@@ -0,0 +1,167 @@
+//! This module provides a serial implementation of Batcher's odd-even merge sort.
+//!
+//! Like the bitonic sort, odd-even merge sort is a sorting network of O(n log^2 n)
+//! compare-exchanges whose sequence depends only on the length of the input. It needs
+//! fewer of them: every comparator sorts in the same direction, and for lengths that are
+//! not a power of two the comparators that would reach past the end are dropped instead
+//! of being rearranged into a descending half. Use [`odd_even_network`] together with
+//! [`bitonic_network`](crate::bitonic_serial::bitonic_network) to compare both networks.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::odd_even_merge_sort::odd_even_merge_sort;
+//!
+//! let mut nums = vec![4, 2, 7, 1, 5];
+//! odd_even_merge_sort(&mut nums);
+//! assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+//! ```
+
+// takes a `Vec` like the entry points of the other networks
+#![allow(clippy::ptr_arg)]
+
+use crate::bitonic_serial::__should_swap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Sorts the given vector in ascending order with Batcher's odd-even merge sort.
+///
+/// Accepts the same inputs as [`bitonic_sort`](crate::bitonic_serial::bitonic_sort):
+/// any length, and incomparable elements such as `NaN` are never swapped.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::odd_even_merge_sort::odd_even_merge_sort;
+///
+/// let mut nums = vec![2.5, -1.0, 3.0, 0.0];
+/// odd_even_merge_sort(&mut nums);
+/// assert_eq!(nums, vec![-1.0, 0.0, 2.5, 3.0]);
+/// ```
+pub fn odd_even_merge_sort<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd + Copy,
+{
+    odd_even_merge_sort_by(nums, |a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+}
+
+/// Sorts the given vector with a custom comparator closure.
+///
+/// Two elements are swapped when `cmp` returns `Ordering::Greater` for them.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::odd_even_merge_sort::odd_even_merge_sort_by;
+///
+/// let mut nums = vec![(1, 'a'), (3, 'b'), (2, 'c')];
+/// odd_even_merge_sort_by(&mut nums, |a, b| b.0.cmp(&a.0));
+/// assert_eq!(nums, vec![(3, 'b'), (2, 'c'), (1, 'a')]);
+/// ```
+pub fn odd_even_merge_sort_by<T, F>(nums: &mut Vec<T>, cmp: F)
+where
+    T: Copy,
+    F: Fn(&T, &T) -> Ordering,
+{
+    __network(nums.len(), |i, j| {
+        if __should_swap(cmp(&nums[i], &nums[j]), false) {
+            nums.swap(i, j);
+        }
+    });
+}
+
+/// Returns the compare-exchange operations of the odd-even merge sort for `len`
+/// elements.
+///
+/// Each `(i, j)` pair, with `i < j`, compares the elements at `i` and `j` and swaps them
+/// if `nums[i] > nums[j]`, exactly as [`odd_even_merge_sort`] does. The pairs are in
+/// execution order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::bitonic_serial::bitonic_network;
+/// use bitonic_sort::odd_even_merge_sort::odd_even_network;
+///
+/// let mut nums = [3, 1, 2];
+/// for (i, j) in odd_even_network(nums.len()) {
+///     if nums[i] > nums[j] {
+///         nums.swap(i, j);
+///     }
+/// }
+/// assert_eq!(nums, [1, 2, 3]);
+/// assert!(odd_even_network(1000).len() < bitonic_network(1000).len());
+/// ```
+pub fn odd_even_network(len: usize) -> Vec<(usize, usize)> {
+    let mut network = Vec::new();
+    __network(len, |i, j| network.push((i, j)));
+    network
+}
+
+// The iterative formulation of the network for power-of-two lengths, skipping every
+// comparator that touches an index at or past `len`. Those would only compare against
+// padding larger than every element, which never swaps, so the rest still sorts.
+fn __network<F>(len: usize, mut compare: F)
+where
+    F: FnMut(usize, usize),
+{
+    // `size` is the length of the sorted runs being merged, `stride` the comparator
+    // distance within the current merge step
+    let mut size = 1;
+    while size < len {
+        let mut stride = size;
+        while stride > 0 {
+            let mut start = stride % size;
+            while start + stride < len {
+                for i in start..(start + stride).min(len - stride) {
+                    // only compare elements of the same pair of runs
+                    if i / (2 * size) == (i + stride) / (2 * size) {
+                        compare(i, i + stride);
+                    }
+                }
+                start += 2 * stride;
+            }
+            stride /= 2;
+        }
+        size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitonic_serial::bitonic_network;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_odd_even_merge_sort() {
+        let mut nums = vec![5, 3, 8, 1, 9, 2];
+        odd_even_merge_sort(&mut nums);
+        assert_eq!(nums, vec![1, 2, 3, 5, 8, 9]);
+    }
+
+    #[test]
+    fn test_odd_even_merge_sort_every_length() {
+        for len in 0..300 {
+            let nums = gen_few_unique(len, 50);
+            let mut sorted = nums.clone();
+            odd_even_merge_sort(&mut sorted);
+            let mut expected = nums;
+            expected.sort();
+            assert!(sorted == expected, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_odd_even_network_size() {
+        // (k^2 - k + 4) * 2^(k - 2) - 1 comparators for 2^k elements
+        for k in 1..10u32 {
+            let len = 1usize << k;
+            let expected = ((k * k - k + 4) as usize) * (1 << k) / 4 - 1;
+            assert_eq!(odd_even_network(len).len(), expected);
+            assert!(odd_even_network(len).len() <= bitonic_network(len).len());
+        }
+        assert!(odd_even_network(0).is_empty());
+        assert!(odd_even_network(1).is_empty());
+    }
+}
@@ -1,88 +1,1401 @@
-use std::sync::Arc;
-use std::{slice, thread};
-struct Wrap<T: ?Sized>(*mut T);
-unsafe impl<T> Send for Wrap<T> {}
-unsafe impl<T> Sync for Wrap<T> {}
+//! This module contains a parallel merge sort: `parallel` partitions are sorted on their
+//! own threads and then merged pairwise.
+//!
+//! # Stability
+//!
+//! [`sort`] is stable and [`sort_unstable`] is not, like their namesakes in the standard
+//! library. Both allocate a scratch buffer of `n` elements for the merges. `sort_unstable`
+//! may reorder equal elements. `sort` is [`stable_parallel_sort`], which sorts the
+//! partitions with the stable `sort_by`, as the merges already keep equal elements in
+//! order. [`parallel_sort_by_keys`] is stable as well, as it sorts each key together with
+//! its input index. Every other sort of this module is unstable.
 
-pub fn parallel_sort<T>(nums: &mut Vec<T>, mut parallel: u8)
+// the `Vec` entry points are kept from when lengths that are not a power of two were
+// padded, which the merges no longer need
+#![allow(clippy::ptr_arg)]
+
+use crate::bitonic_serial;
+use crate::error::SortError;
+use crate::pool::{self, ThreadPool};
+use crate::validate;
+use std::cmp::Ordering;
+use std::mem;
+use std::sync::atomic::{self, AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Sorts the given vector by sorting `parallel` partitions on their own threads and
+/// merging them pairwise.
+///
+/// A `parallel` of `0` or `1` sorts serially on the calling thread. Other values are
+/// used as they are, so `6` sorts six partitions on six threads: the partitions' lengths
+/// differ by at most one, and when a merge pass has an odd number of runs the last one
+/// waits for the next pass. Panics if two elements are not comparable, see
+/// [`try_parallel_sort`].
+///
+/// The threads are named `bitonic-sort-<n>`, and a panic in one of them is resumed on
+/// the calling thread with the partition or the merged ranges in front of its message.
+/// The values themselves are not printed, as `T` need not be `Debug`; the index of an
+/// incomparable element is what [`try_parallel_sort`] reports.
+///
+/// The partitions may have any length, so nothing is padded and the vector never grows.
+/// The merges allocate a scratch buffer of `n` elements, which
+/// [`parallel_sort_with_scratch`] takes from the caller instead.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5, 3, 6];
+/// parallel_sort(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+/// ```
+pub fn parallel_sort<T>(nums: &mut Vec<T>, parallel: usize)
 where
     T: PartialOrd + Send + Sync + Copy,
 {
-    if nums.is_empty() {
+    let pool = __pool(nums.len(), parallel);
+    parallel_sort_with_pool(nums, parallel, &pool);
+}
+
+/// Sorts the given vector like [`parallel_sort`], running the work on a borrowed pool.
+///
+/// The pool's threads are reused across calls, which avoids the thread creation cost
+/// when sorting many small inputs. `parallel` still controls the number of partitions;
+/// the number of threads doing the work is the pool size plus the calling thread.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_with_pool;
+/// use bitonic_sort::pool::ThreadPool;
+///
+/// let pool = ThreadPool::new(1);
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// parallel_sort_with_pool(&mut nums, 2, &pool);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn parallel_sort_with_pool<T>(nums: &mut Vec<T>, parallel: usize, pool: &ThreadPool)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    __sort(
+        nums,
+        parallel,
+        &|x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable"),
+        pool,
+        None,
+    );
+}
+
+/// Returns a sorted copy of `nums`, leaving the input untouched.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sorted;
+///
+/// let nums = [4, 2, 7, 1, 5];
+/// assert_eq!(parallel_sorted(&nums, 2), vec![1, 2, 4, 5, 7]);
+/// assert_eq!(nums, [4, 2, 7, 1, 5]);
+/// ```
+pub fn parallel_sorted<T>(nums: &[T], parallel: usize) -> Vec<T>
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let mut sorted = nums.to_vec();
+    parallel_sort(&mut sorted, parallel);
+    sorted
+}
+
+/// Sorts `nums` like [`parallel_sort`] and returns it as a boxed slice.
+///
+/// `into_boxed_slice` drops any spare capacity of the vector, so the result takes only the
+/// memory its elements need.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_boxed;
+///
+/// let sorted = parallel_sort_boxed(vec![4, 2, 7, 1, 5], 2);
+/// assert_eq!(*sorted, [1, 2, 4, 5, 7]);
+/// ```
+pub fn parallel_sort_boxed<T>(mut nums: Vec<T>, parallel: usize) -> Box<[T]>
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    parallel_sort(&mut nums, parallel);
+    nums.into_boxed_slice()
+}
+
+/// Sorts the given vector using a thread count derived from the machine.
+///
+/// The degree of parallelism is the largest power of two not exceeding
+/// `std::thread::available_parallelism()`, further capped so that no thread gets fewer
+/// than two elements. If the core count cannot be determined the vector is sorted
+/// serially with `sort_unstable_by` instead.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_auto;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// parallel_sort_auto(&mut nums);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn parallel_sort_auto<T>(nums: &mut Vec<T>)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    match crate::auto_parallel(nums.len()) {
+        Some(parallel) => parallel_sort(nums, parallel),
+        None => {
+            nums.sort_unstable_by(|x, y| x.partial_cmp(y).expect("elements are not comparable"))
+        }
+    }
+}
+
+/// Sorts the given vector of totally ordered elements in parallel.
+///
+/// This compares with `Ord::cmp` directly, so unlike [`parallel_sort`] it can never
+/// panic on an incomparable pair of elements.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_ord;
+///
+/// let mut nums = vec![4u64, 2, 7, 1, 5, 3, 6];
+/// parallel_sort_ord(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+/// ```
+pub fn parallel_sort_ord<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: Ord + Send + Sync + Copy,
+{
+    let pool = __pool(nums.len(), parallel);
+    __sort(nums, parallel, &T::cmp, &pool, None);
+}
+
+/// Sorts the given slice in place, for callers that do not own a `Vec`.
+///
+/// This is [`parallel_sort`] without the `Vec`: the partitions may have any length, so
+/// the slice is sorted where it is, with a scratch buffer of `n` elements for the merges.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_slice;
+///
+/// let mut nums = [4, 2, 7, 1, 5];
+/// parallel_sort_slice(&mut nums, 2);
+/// assert_eq!(nums, [1, 2, 4, 5, 7]);
+/// ```
+pub fn parallel_sort_slice<T>(nums: &mut [T], parallel: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let pool = __pool(nums.len(), parallel);
+    __sort(nums, parallel, &cmp, &pool, None);
+}
+
+/// Sorts the given vector like [`parallel_sort`], keeping equal elements in their input
+/// order.
+///
+/// The partitions are sorted with the stable `sort_by` instead of `sort_unstable_by`,
+/// and every merge takes from the left run on ties, so equal elements keep their input
+/// order end to end. This allocates a scratch buffer of `n` elements for the merges, like
+/// [`parallel_sort`], and `sort_by` up to half a partition on each thread.
+/// Stability only shows for compound records whose `PartialOrd` looks at part of the
+/// record; equal numbers are indistinguishable anyway.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::stable_parallel_sort;
+/// use std::cmp::Ordering;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct Order {
+///     day: u32,
+///     id: u32,
+/// }
+///
+/// impl PartialOrd for Order {
+///     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+///         self.day.partial_cmp(&other.day)
+///     }
+/// }
+///
+/// let mut orders = vec![
+///     Order { day: 2, id: 1 },
+///     Order { day: 1, id: 2 },
+///     Order { day: 2, id: 3 },
+///     Order { day: 1, id: 4 },
+/// ];
+/// stable_parallel_sort(&mut orders, 2);
+/// let ids: Vec<u32> = orders.iter().map(|order| order.id).collect();
+/// assert_eq!(ids, vec![2, 4, 1, 3]);
+/// ```
+pub fn stable_parallel_sort<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let mut scratch = nums.clone();
+    let pool = __pool(nums.len(), parallel);
+    __sort_runs(
+        nums,
+        &mut scratch,
+        parallel,
+        &|x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable"),
+        &pool,
+        None,
+        RunSort::Stable,
+    );
+}
+
+/// Sorts the given vector in ascending order, keeping equal elements in their input
+/// order.
+///
+/// This is [`stable_parallel_sort`] under the standard library's name.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::sort;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// sort(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn sort<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    stable_parallel_sort(nums, parallel);
+}
+
+/// Sorts the given vector in ascending order without keeping equal elements in their
+/// input order.
+///
+/// This is [`parallel_sort`] under the standard library's name.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::sort_unstable;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// sort_unstable(&mut nums, 2);
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+/// ```
+pub fn sort_unstable<T>(nums: &mut Vec<T>, parallel: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    parallel_sort(nums, parallel);
+}
+
+/// Sorts the given vector like [`parallel_sort`] and returns the number of inversions
+/// the input had.
+///
+/// An inversion is a pair of positions `i < j` with `nums[i] > nums[j]`; equal elements
+/// do not count. A sorted input has none and a reversed one of distinct elements has
+/// `n(n-1)/2`. The count comes with the merges: each thread counts the inversions within
+/// its partition while merge sorting it, each pairwise merge counts those between its two
+/// runs, and the per-thread counts are summed.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_count_inversions;
+///
+/// let mut nums = vec![3, 1, 2, 5, 4];
+/// assert_eq!(parallel_sort_count_inversions(&mut nums, 2), 3);
+/// assert_eq!(nums, vec![1, 2, 3, 4, 5]);
+/// ```
+pub fn parallel_sort_count_inversions<T>(nums: &mut Vec<T>, parallel: usize) -> u64
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let inversions = AtomicU64::new(0);
+    let mut scratch = nums.clone();
+    let pool = __pool(nums.len(), parallel);
+    __sort_runs(
+        nums,
+        &mut scratch,
+        parallel,
+        &|x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable"),
+        &pool,
+        None,
+        RunSort::CountInversions(&inversions),
+    );
+    inversions.into_inner()
+}
+
+/// Sorts the given vector like [`parallel_sort`] and removes consecutive duplicates,
+/// returning the number of elements left.
+///
+/// The result is that of sorting and then calling [`Vec::dedup`], but the duplicates are
+/// dropped during the final merge instead of in a pass of their own: the two halves are
+/// sorted with `parallel / 2` threads each, and merging them skips every element equal
+/// to the last one written. Like `dedup`, that compares elements with `==`.
+///
+/// # Panics
+///
+/// Panics if two elements are not comparable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::sort_dedup;
+///
+/// let mut nums = vec![4, 2, 7, 2, 4, 4, 1];
+/// assert_eq!(sort_dedup(&mut nums, 2), 4);
+/// assert_eq!(nums, vec![1, 2, 4, 7]);
+/// ```
+pub fn sort_dedup<T>(nums: &mut Vec<T>, parallel: usize) -> usize
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    if nums.len() <= 1 {
+        return nums.len();
+    }
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let pool = __pool(nums.len(), parallel);
+    let mid = nums.len() / 2;
+    let mut merged = nums.clone();
+    {
+        let (left, right) = nums.split_at_mut(mid);
+        let (left_scratch, right_scratch) = merged.split_at_mut(mid);
+        let (cmp, pool) = (&cmp, &pool);
+        let parallel = (parallel / 2).max(1);
+        pool.scope(|s| {
+            s.spawn(move || {
+                __sort_runs(
+                    left,
+                    left_scratch,
+                    parallel,
+                    cmp,
+                    pool,
+                    None,
+                    RunSort::Unstable,
+                );
+            });
+            __sort_runs(
+                right,
+                right_scratch,
+                parallel,
+                cmp,
+                pool,
+                None,
+                RunSort::Unstable,
+            );
+        });
+    }
+    merged.clear();
+    let (left, right) = nums.split_at(mid);
+    let (mut l, mut r) = (0, 0);
+    while l < left.len() || r < right.len() {
+        let next = if r == right.len()
+            || (l < left.len() && cmp(&left[l], &right[r]) != Ordering::Greater)
+        {
+            l += 1;
+            left[l - 1]
+        } else {
+            r += 1;
+            right[r - 1]
+        };
+        if merged.last() != Some(&next) {
+            merged.push(next);
+        }
+    }
+    *nums = merged;
+    nums.len()
+}
+
+/// Sorts the given slice like [`parallel_sort`], choosing the number of partitions and
+/// the number of threads merging them separately.
+///
+/// `nums` is cut into `initial_partitions` partitions, each sorted on a thread of its
+/// own, and the merge passes then run on `merge_threads` threads. With both equal this
+/// is the split [`parallel_sort`] uses for a `parallel` of that value. Finer partitions
+/// with fewer merge threads can pay off where threads are expensive to share memory
+/// between, such as across NUMA nodes. A pass merges pairs of runs, so more merge
+/// threads than half the partitions stay idle. Values of `0` count as `1`. Nothing is
+/// padded; the merges allocate a scratch buffer of `n` elements.
+///
+/// # Panics
+///
+/// Panics if two elements are not comparable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_tuned;
+///
+/// let mut nums = [4, 2, 7, 1, 5, 3, 6, 8];
+/// parallel_sort_tuned(&mut nums, 8, 2);
+/// assert_eq!(nums, [1, 2, 3, 4, 5, 6, 7, 8]);
+/// ```
+pub fn parallel_sort_tuned<T>(nums: &mut [T], initial_partitions: usize, merge_threads: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let bounds = __run_bounds(nums.len(), __run_count(nums.len(), initial_partitions));
+    let run_pool = __pool(nums.len(), initial_partitions);
+    __sort_partitions(nums, &bounds, &cmp, &run_pool, RunSort::Unstable);
+    if bounds.len() <= 2 {
         return;
     }
-    let origin_len = nums.len();
-    if !origin_len.is_power_of_two() {
-        let max = *nums.iter().fold(
-            nums.first().unwrap(),
-            |max, x| if max > x { max } else { x },
-        );
-        nums.resize(origin_len.next_power_of_two(), max);
+    // the partitions' pool serves the merges too unless they are to use fewer threads
+    let merge_pool;
+    let pool = if merge_threads.max(1) < initial_partitions.max(1) {
+        drop(run_pool);
+        merge_pool = __pool(nums.len(), merge_threads);
+        &merge_pool
+    } else {
+        &run_pool
+    };
+    let mut scratch = nums.to_vec();
+    __merge_runs(
+        nums,
+        &mut scratch,
+        bounds,
+        &cmp,
+        pool,
+        None,
+        RunSort::Unstable,
+    );
+    validate::__debug_assert_sorted(nums, false, &cmp);
+}
+
+/// The time [`parallel_sort_stats`] spent in each phase of the sort.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortStats {
+    /// Wall-clock time from starting the partitions' sorts until the last one finished.
+    pub leaf_sort: Duration,
+    /// Wall-clock time of all merge passes together.
+    pub merge: Duration,
+    /// The number of merge passes, `ceil(log2(partitions))`.
+    pub passes: usize,
+}
+
+/// Sorts the given slice like [`parallel_sort_with_scratch`] and returns how long its
+/// phases took.
+///
+/// The calling thread measures each phase between the barriers that wait for all of its
+/// threads, so the durations are wall-clock time, not summed over threads. A long leaf
+/// phase suggests more partitions, a long merge phase more merge threads, see
+/// [`parallel_sort_tuned`]. Starting the threads and allocating the scratch buffer of
+/// `n` elements count towards neither.
+///
+/// # Panics
+///
+/// Panics if two elements are not comparable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_stats;
+///
+/// let mut nums: Vec<u32> = (0..1000).rev().collect();
+/// let stats = parallel_sort_stats(&mut nums, 4);
+/// assert_eq!(stats.passes, 2);
+/// assert!(nums.windows(2).all(|pair| pair[0] <= pair[1]));
+/// ```
+pub fn parallel_sort_stats<T>(nums: &mut [T], parallel: usize) -> SortStats
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let runs = __run_count(nums.len(), parallel);
+    let bounds = __run_bounds(nums.len(), runs);
+    let pool = __pool(nums.len(), parallel);
+    let mut scratch = if runs > 1 { nums.to_vec() } else { Vec::new() };
+
+    let start = Instant::now();
+    __sort_partitions(nums, &bounds, &cmp, &pool, RunSort::Unstable);
+    let leaf_sort = start.elapsed();
+
+    let start = Instant::now();
+    __merge_runs(
+        nums,
+        &mut scratch,
+        bounds,
+        &cmp,
+        &pool,
+        None,
+        RunSort::Unstable,
+    );
+    let merge = start.elapsed();
+
+    validate::__debug_assert_sorted(nums, false, &cmp);
+    SortStats {
+        leaf_sort,
+        merge,
+        passes: runs.next_power_of_two().ilog2() as usize,
+    }
+}
+
+/// Sorts the given slice like [`parallel_sort`], using a caller-provided scratch buffer.
+///
+/// `scratch` must be exactly as long as `nums`; its contents are ignored and left
+/// unspecified. The merge passes alternate between the two slices and the partitions
+/// may have any length, so no padding or buffer is allocated, and repeated calls can
+/// reuse the same scratch. The worker threads and their job queue still allocate a
+/// small amount that does not depend on the input; with a `parallel` of `0` or `1` the
+/// sort runs on the calling thread and does not allocate at all.
+///
+/// # Panics
+///
+/// Panics if `scratch.len() != nums.len()`, or if two elements are not comparable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_with_scratch;
+///
+/// let mut scratch = vec![0; 5];
+/// let mut nums = [4, 2, 7, 1, 5];
+/// parallel_sort_with_scratch(&mut nums, &mut scratch, 2);
+/// assert_eq!(nums, [1, 2, 4, 5, 7]);
+/// ```
+pub fn parallel_sort_with_scratch<T>(nums: &mut [T], scratch: &mut [T], parallel: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    assert_eq!(
+        nums.len(),
+        scratch.len(),
+        "scratch must be as long as the input"
+    );
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let pool = __pool(nums.len(), parallel);
+    __sort_runs(
+        nums,
+        scratch,
+        parallel,
+        &cmp,
+        &pool,
+        None,
+        RunSort::Unstable,
+    );
+    validate::__debug_assert_sorted(nums, false, &cmp);
+}
+
+/// Sorts `keys` in ascending order and applies the same permutation to `data`.
+///
+/// `keys[i]` is the precomputed sort key of `data[i]`. Only the keys are compared, as
+/// plain integers, so `T` needs no order at all and is never copied: the keys are sorted
+/// together with their indices like in [`parallel_sort`], and `data` is then permuted in
+/// place with one swap per misplaced element. This is the fast way to sort large records
+/// by a cheap key. Elements with equal keys keep their input order.
+///
+/// # Panics
+///
+/// Panics if `keys.len() != data.len()`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_by_keys;
+///
+/// let mut data = vec!["carol", "alice", "dave", "bob"];
+/// let mut keys = vec![30, 10, 40, 10];
+/// parallel_sort_by_keys(&mut data, &mut keys, 2);
+/// assert_eq!(data, vec!["alice", "bob", "carol", "dave"]);
+/// assert_eq!(keys, vec![10, 10, 30, 40]);
+/// ```
+pub fn parallel_sort_by_keys<T>(data: &mut [T], keys: &mut [u64], parallel: usize) {
+    assert_eq!(data.len(), keys.len(), "there must be one key per element");
+    let mut pairs: Vec<(u64, usize)> = keys.iter().copied().zip(0..).collect();
+    let mut scratch = pairs.clone();
+    let cmp = |x: &(u64, usize), y: &(u64, usize)| x.cmp(y);
+    let pool = __pool(data.len(), parallel);
+    __sort_runs(
+        &mut pairs,
+        &mut scratch,
+        parallel,
+        &cmp,
+        &pool,
+        None,
+        RunSort::Unstable,
+    );
+    for (key, &(sorted, _)) in keys.iter_mut().zip(&pairs) {
+        *key = sorted;
     }
+    bitonic_serial::__permute(data, &mut pairs);
+}
+
+/// Writes the elements of `src` into `dst` in ascending order, leaving `src` untouched.
+///
+/// This is [`parallel_sort`] with the output going to a caller-provided slice, so the
+/// original order is kept around and nothing is padded: `dst` must be exactly as long as
+/// `src`, and its previous contents are overwritten. The merges need a scratch buffer of
+/// `n` elements, which is allocated internally; use [`parallel_sort_with_scratch`] on a
+/// copy to provide that one as well.
+///
+/// # Panics
+///
+/// Panics if `dst.len() != src.len()`, or if two elements are not comparable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_to;
+///
+/// let src = [4, 2, 7, 1, 5];
+/// let mut dst = [0; 5];
+/// parallel_sort_to(&src, &mut dst, 2);
+/// assert_eq!(dst, [1, 2, 4, 5, 7]);
+/// assert_eq!(src, [4, 2, 7, 1, 5]);
+/// ```
+pub fn parallel_sort_to<T>(src: &[T], dst: &mut [T], parallel: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    assert_eq!(
+        src.len(),
+        dst.len(),
+        "destination must be as long as the source"
+    );
+    dst.copy_from_slice(src);
+    let mut scratch = src.to_vec();
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let pool = __pool(src.len(), parallel);
+    __sort_runs(
+        dst,
+        &mut scratch,
+        parallel,
+        &cmp,
+        &pool,
+        None,
+        RunSort::Unstable,
+    );
+    validate::__debug_assert_sorted(dst, false, &cmp);
+}
+
+/// Sorts the given slice like [`parallel_sort`] with at most `max_scratch_bytes` of
+/// extra memory for the elements.
+///
+/// The `parallel` partitions are sorted in place, and the merge passes share a scratch
+/// budget of `max_scratch_bytes / size_of::<T>()` elements, split evenly between the
+/// merges running at the same time. A merge whose shorter run fits into its share copies
+/// that run out and merges in linear time, like [`parallel_sort`]. A merge with less
+/// scratch splits both runs around a binary-searched element and rotates the middle into
+/// place until the pieces fit, which costs up to O(n log n) moves per pass at a budget of
+/// zero. The passes stay the same, so the throughput degrades gradually as the budget
+/// shrinks, and the first passes, with many short merges, are slowed down first.
+///
+/// Peak extra memory for elements never exceeds `max_scratch_bytes`: nothing is padded
+/// and no full-length buffer is allocated. The worker threads, their job queue and the
+/// O(log n) recursion of the merges take a small amount besides that.
+///
+/// # Panics
+///
+/// Panics if two elements are not comparable.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_bounded;
+///
+/// let mut nums: Vec<u64> = (0..10_000).rev().collect();
+/// // a thousand bytes, room for 125 elements
+/// parallel_sort_bounded(&mut nums, 4, 1000);
+/// assert!(nums.windows(2).all(|pair| pair[0] <= pair[1]));
+/// ```
+pub fn parallel_sort_bounded<T>(nums: &mut [T], parallel: usize, max_scratch_bytes: usize)
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let cmp = |x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable");
+    let budget = max_scratch_bytes / mem::size_of::<T>().max(1);
     let len = nums.len();
-    parallel = if parallel < 1 {
-        1
+    let runs = __run_count(len, parallel);
+    let mut bounds = __run_bounds(len, runs);
+    let pool = __pool(len, parallel);
+    pool.scope(|s| {
+        let mut rest = &mut nums[..];
+        for (i, run) in bounds.windows(2).enumerate() {
+            let (run, tail) = mem::take(&mut rest).split_at_mut(run[1] - run[0]);
+            rest = tail;
+            s.spawn(move || {
+                pool::with_context(
+                    || format!("sorting partition {} of {}", i, runs),
+                    || run.sort_unstable_by(cmp),
+                )
+            });
+        }
+    });
+    while bounds.len() > 2 {
+        let merges = (bounds.len() - 1) / 2;
+        let share = budget / merges;
+        pool.scope(|s| {
+            let mut rest = &mut nums[..];
+            let mut offset = 0;
+            for pair in bounds.windows(3).step_by(2) {
+                let (_, tail) = mem::take(&mut rest).split_at_mut(pair[0] - offset);
+                let (region, tail) = tail.split_at_mut(pair[2] - pair[0]);
+                rest = tail;
+                offset = pair[2];
+                let (lo, mid, hi) = (pair[0], pair[1], pair[2]);
+                s.spawn(move || {
+                    pool::with_context(
+                        || format!("merging elements {}..{} and {}..{}", lo, mid, mid, hi),
+                        || {
+                            let mut buf = Vec::with_capacity(share.min(region.len() / 2));
+                            __merge_bounded(region, mid - lo, &mut buf, share, &cmp);
+                        },
+                    )
+                });
+            }
+        });
+        bounds = bounds.iter().step_by(2).copied().collect();
+        if bounds.last() != Some(&len) {
+            bounds.push(len);
+        }
+    }
+    validate::__debug_assert_sorted(nums, false, &cmp);
+}
+
+/// Sorts the given vector like [`parallel_sort`], but reports incomparable elements
+/// instead of panicking inside a worker thread.
+///
+/// Before sorting, the input is scanned in parallel for elements that do not compare
+/// with themselves (such as `NaN`). The scan stops as soon as every worker has passed
+/// the first offending element, whose index is returned in
+/// [`SortError::Incomparable`], and the vector is left untouched.
+///
+/// Elements that compare with themselves can still fail to compare with each other, like
+/// numbers ordered by divisibility. The sort stops at the first such pair it runs into,
+/// like a cancelled one: the workers stop comparing, no further merge pass starts, and
+/// the vector holds its original elements in an unspecified order. The index returned
+/// then is that of an element of the vector as left behind that does not compare with
+/// the other element of the pair.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::error::SortError;
+/// use bitonic_sort::parallel_sort::try_parallel_sort;
+///
+/// let mut nums = vec![3.0, 1.0, f64::NAN, 2.0];
+/// assert_eq!(try_parallel_sort(&mut nums, 2), Err(SortError::Incomparable { index: 2 }));
+///
+/// let mut nums = vec![3.0, 1.0, 2.0];
+/// assert_eq!(try_parallel_sort(&mut nums, 2), Ok(()));
+/// assert_eq!(nums, vec![1.0, 2.0, 3.0]);
+/// ```
+pub fn try_parallel_sort<T>(nums: &mut Vec<T>, parallel: usize) -> Result<(), SortError>
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let pool = __pool(nums.len(), parallel);
+    if let Some(index) = __find_incomparable(nums, parallel, &pool) {
+        return Err(SortError::Incomparable { index });
+    }
+    // once a pair fails to compare, every comparison is a cheap `Equal`, which lets the
+    // running partition sorts finish without looking at the elements, and the flag
+    // keeps the next merge pass from starting
+    let stop = AtomicBool::new(false);
+    let incomparable = Mutex::new(None);
+    __sort(
+        nums,
+        parallel,
+        &|x: &T, y: &T| {
+            if stop.load(atomic::Ordering::Relaxed) {
+                return Ordering::Equal;
+            }
+            x.partial_cmp(y).unwrap_or_else(|| {
+                let mut first = incomparable.lock().unwrap_or_else(PoisonError::into_inner);
+                first.get_or_insert(*x);
+                stop.store(true, atomic::Ordering::Relaxed);
+                Ordering::Equal
+            })
+        },
+        &pool,
+        Some(&|| stop.load(atomic::Ordering::Relaxed)),
+    );
+    let Some(x) = incomparable
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+    else {
+        return Ok(());
+    };
+    // the element `x` failed to compare with was only moved, so the scan finds it, or
+    // another element that does not compare with `x` before it
+    let index = nums
+        .iter()
+        .position(|z| z.partial_cmp(&x).is_none())
+        .expect("the sort keeps every element");
+    Err(SortError::Incomparable { index })
+}
+
+/// Where [`parallel_sort_nan`] places the `NaN`s of its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// Every `NaN` goes before all other values.
+    First,
+    /// Every `NaN` goes after all other values.
+    Last,
+    /// A `NaN` fails the sort with [`SortError::Incomparable`].
+    Error,
+}
+
+/// Sorts a vector of floats that may contain `NaN`, placing the `NaN`s as `policy` says.
+///
+/// Other values are compared with `f64::total_cmp`, so `-0.0` sorts before `0.0` and
+/// nothing can panic. `NaN`s are moved to the front or the back regardless of their
+/// sign bit and payload, which `total_cmp` alone would use to split them between both
+/// ends. With [`NanPolicy::Error`], the index of the first `NaN` is returned like by
+/// [`try_parallel_sort`] and the vector is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::error::SortError;
+/// use bitonic_sort::parallel_sort::{parallel_sort_nan, NanPolicy};
+///
+/// let mut nums = vec![2.0, f64::NAN, -1.0, -f64::NAN, 0.5];
+/// parallel_sort_nan(&mut nums, 2, NanPolicy::Last).unwrap();
+/// assert_eq!(nums[..3], [-1.0, 0.5, 2.0]);
+/// assert!(nums[3..].iter().all(|x| x.is_nan()));
+///
+/// let mut nums = vec![2.0, f64::NAN, -1.0];
+/// let result = parallel_sort_nan(&mut nums, 2, NanPolicy::Error);
+/// assert_eq!(result, Err(SortError::Incomparable { index: 1 }));
+/// ```
+pub fn parallel_sort_nan(
+    nums: &mut Vec<f64>,
+    parallel: usize,
+    policy: NanPolicy,
+) -> Result<(), SortError> {
+    // `NaN` is the only float that does not compare, even with itself, so the scan finds
+    // every incomparable element
+    let pool = __pool(nums.len(), parallel);
+    if policy == NanPolicy::Error {
+        if let Some(index) = __find_incomparable(nums, parallel, &pool) {
+            return Err(SortError::Incomparable { index });
+        }
+    }
+    let nan = if policy == NanPolicy::First {
+        Ordering::Less
     } else {
-        parallel.checked_next_power_of_two().unwrap_or(u8::MAX)
+        Ordering::Greater
     };
-    let mut size = len / parallel as usize;
-    if size < 1 {
-        size = 1;
-        parallel = len as u8;
-    }
-    let shared_ptr = Arc::new(Wrap(nums.as_mut_ptr()));
-    thread::scope(|s| {
-        let mut handles = Vec::new();
-        for i in 0..parallel as usize {
-            let shared_ptr = shared_ptr.clone();
-            handles.push(s.spawn(move || {
-                let shared_slice = unsafe { slice::from_raw_parts_mut(shared_ptr.0, len) };
-                shared_slice[i * size..(i + 1) * size]
-                    .sort_unstable_by(|x, y| x.partial_cmp(y).expect("float error!"));
-            }));
-        }
-        for handle in handles {
-            handle.join().unwrap();
-        }
-        while parallel > 1 {
-            parallel /= 2;
-            size *= 2;
-            let mut handles = Vec::new();
-            for i in 0..parallel as usize {
-                let shared_ptr = shared_ptr.clone();
-                handles.push(s.spawn(move || {
-                    let shared_slice = unsafe {
-                        slice::from_raw_parts_mut(shared_ptr.0, len)
-                            .get_unchecked_mut(i * size..(i + 1) * size)
-                    };
-                    let mut tmp = Vec::with_capacity(size);
-                    let (lb, rb) = (size / 2, size);
-                    let (mut l, mut r) = (0, size / 2);
-                    while l < lb && r < rb {
-                        if shared_slice[l] <= shared_slice[r] {
-                            tmp.push(shared_slice[l].clone());
-                            l += 1;
-                        } else {
-                            tmp.push(shared_slice[r].clone());
-                            r += 1;
-                        }
+    __sort(
+        nums,
+        parallel,
+        &|x: &f64, y: &f64| match (x.is_nan(), y.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => nan,
+            (false, true) => nan.reverse(),
+            (false, false) => x.total_cmp(y),
+        },
+        &pool,
+        None,
+    );
+    Ok(())
+}
+
+/// Sorts a vector of floats by `f64::total_cmp`, the IEEE 754 total order.
+///
+/// Unlike [`parallel_sort_nan`], every bit pattern has its own place: `-0.0` sorts before
+/// `0.0`, subnormals sit between zero and the smallest normal numbers of their sign, and
+/// `NaN`s go to the front or the back by their sign bit, ordered by payload. Elements
+/// that end up next to each other are equal bit for bit, so the output is the same on
+/// every platform and for every `parallel`. Nothing can panic.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::parallel_sort::parallel_sort_total_f64;
+///
+/// let mut nums = vec![0.0, f64::NAN, -0.0, -1.5, -f64::NAN, 1e-310];
+/// parallel_sort_total_f64(&mut nums, 2);
+/// assert!(nums[0].is_nan() && nums[0].is_sign_negative());
+/// assert_eq!(nums[1], -1.5);
+/// assert!(nums[2] == 0.0 && nums[2].is_sign_negative());
+/// assert!(nums[3] == 0.0 && nums[3].is_sign_positive());
+/// assert_eq!(nums[4], 1e-310);
+/// assert!(nums[5].is_nan() && nums[5].is_sign_positive());
+/// ```
+pub fn parallel_sort_total_f64(nums: &mut Vec<f64>, parallel: usize) {
+    let pool = __pool(nums.len(), parallel);
+    __sort(nums, parallel, &f64::total_cmp, &pool, None);
+}
+
+/// Sorts the given vector like [`parallel_sort`], giving up early once `cancel` is set.
+///
+/// The flag is polled before the partitions are sorted and before every merge pass.
+/// When a sort stops early it returns [`SortError::Cancelled`], and the vector holds its
+/// original elements in an unspecified order.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::error::SortError;
+/// use bitonic_sort::parallel_sort::parallel_sort_cancellable;
+/// use std::sync::atomic::AtomicBool;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// assert_eq!(parallel_sort_cancellable(&mut nums, 2, &AtomicBool::new(false)), Ok(()));
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+///
+/// let cancel = AtomicBool::new(true);
+/// assert_eq!(parallel_sort_cancellable(&mut nums, 2, &cancel), Err(SortError::Cancelled));
+/// ```
+pub fn parallel_sort_cancellable<T>(
+    nums: &mut Vec<T>,
+    parallel: usize,
+    cancel: &AtomicBool,
+) -> Result<(), SortError>
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let pool = __pool(nums.len(), parallel);
+    let completed = __sort(
+        nums,
+        parallel,
+        &|x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable"),
+        &pool,
+        Some(&|| cancel.load(atomic::Ordering::Relaxed)),
+    );
+    if !completed {
+        return Err(SortError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Sorts the given vector like [`parallel_sort`], giving up once `budget` has elapsed.
+///
+/// The time is checked on the calling thread before the partitions are sorted and
+/// before every merge pass, not between comparisons: a pass that has started always
+/// finishes, so a sort can overrun its budget by up to one pass, and sorting the
+/// partitions is the longest of them. When the budget runs out the sort returns
+/// [`SortError::TimedOut`], and the vector holds its original elements, only partially
+/// sorted: each run of the last finished pass is in order, but the runs are not merged.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::error::SortError;
+/// use bitonic_sort::parallel_sort::parallel_sort_timeout;
+/// use std::time::Duration;
+///
+/// let mut nums = vec![4, 2, 7, 1, 5];
+/// assert_eq!(parallel_sort_timeout(&mut nums, 2, Duration::from_secs(60)), Ok(()));
+/// assert_eq!(nums, vec![1, 2, 4, 5, 7]);
+///
+/// let result = parallel_sort_timeout(&mut nums, 2, Duration::ZERO);
+/// assert_eq!(result, Err(SortError::TimedOut));
+/// ```
+pub fn parallel_sort_timeout<T>(
+    nums: &mut Vec<T>,
+    parallel: usize,
+    budget: Duration,
+) -> Result<(), SortError>
+where
+    T: PartialOrd + Send + Sync + Copy,
+{
+    let start = Instant::now();
+    let pool = __pool(nums.len(), parallel);
+    let completed = __sort(
+        nums,
+        parallel,
+        &|x: &T, y: &T| x.partial_cmp(y).expect("elements are not comparable"),
+        &pool,
+        Some(&|| start.elapsed() >= budget),
+    );
+    if !completed {
+        return Err(SortError::TimedOut);
+    }
+    Ok(())
+}
+
+// The index of the first element of `nums` that does not compare with itself, scanned
+// in as many chunks as the sort has runs, on the sort's `pool`. Elements that only fail
+// to compare with others are not found.
+fn __find_incomparable<T>(nums: &[T], parallel: usize, pool: &ThreadPool) -> Option<usize>
+where
+    T: PartialOrd + Sync,
+{
+    if nums.is_empty() {
+        return None;
+    }
+    let size = nums.len().div_ceil(__run_count(nums.len(), parallel));
+    let first = AtomicUsize::new(usize::MAX);
+    pool.scope(|s| {
+        for (i, chunk) in nums.chunks(size).enumerate() {
+            let first = &first;
+            s.spawn(move || {
+                for (j, x) in chunk.iter().enumerate() {
+                    let index = i * size + j;
+                    if index > first.load(atomic::Ordering::Relaxed) {
+                        return;
+                    }
+                    if x.partial_cmp(x).is_none() {
+                        first.fetch_min(index, atomic::Ordering::Relaxed);
+                        return;
                     }
-                    tmp.extend_from_slice(&shared_slice[l..lb]);
-                    tmp.extend_from_slice(&shared_slice[r..rb]);
-                    shared_slice.copy_from_slice(&tmp[..]);
-                }));
+                }
+            });
+        }
+    });
+    match first.into_inner() {
+        usize::MAX => None,
+        index => Some(index),
+    }
+}
+
+// The pool for sorting `len` elements in `parallel` partitions: there are no more of
+// them than elements, see `__run_count`, and the calling thread sorts one of them
+// itself, so it needs one worker fewer.
+fn __pool(len: usize, parallel: usize) -> ThreadPool {
+    ThreadPool::new(__run_count(len, parallel) - 1)
+}
+
+// Sorts `nums` with a scratch buffer allocated for it, see `__sort_runs`. Returns
+// `false` if `cancel` returned `true` and stopped the sort early.
+fn __sort<T, F>(
+    nums: &mut [T],
+    parallel: usize,
+    cmp: &F,
+    pool: &ThreadPool,
+    cancel: Option<&dyn Fn() -> bool>,
+) -> bool
+where
+    T: Send + Sync + Copy,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    // the merge passes ping-pong between `nums` and one scratch buffer allocated up front
+    let mut scratch = if parallel > 1 {
+        nums.to_vec()
+    } else {
+        Vec::new()
+    };
+    let completed = __sort_runs(
+        nums,
+        &mut scratch,
+        parallel,
+        cmp,
+        pool,
+        cancel,
+        RunSort::Unstable,
+    );
+    if completed {
+        validate::__debug_assert_sorted(nums, false, cmp);
+    }
+    completed
+}
+
+// How `__sort_runs` sorts each run before merging the runs.
+#[derive(Clone, Copy)]
+pub(crate) enum RunSort<'a> {
+    Unstable,
+    // the merges take from the left run on ties, so stable runs make a stable sort
+    Stable,
+    // a merge sort adding the inversions within its run to the counter, which the
+    // merges then add those between the runs to
+    CountInversions(&'a AtomicU64),
+}
+
+// Sorts `parallel` runs of `nums` on their own threads, then merges neighbouring runs
+// pass by pass, alternating between `nums` and `scratch`, which must be as long as
+// `nums` unless there is a single run. Runs start at `i * len / runs`, so any length
+// works. `cancel` is asked before the runs are sorted and before every merge pass, and
+// if it returns `true` this returns `false` without sorting any further.
+// `run_sort` picks how the runs are sorted; when it counts inversions, every merge adds
+// those between its two runs.
+pub(crate) fn __sort_runs<T, F>(
+    nums: &mut [T],
+    scratch: &mut [T],
+    parallel: usize,
+    cmp: &F,
+    pool: &ThreadPool,
+    cancel: Option<&dyn Fn() -> bool>,
+    run_sort: RunSort<'_>,
+) -> bool
+where
+    T: Send + Sync + Copy,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    // `bounds[i]..bounds[i + 1]` is the `i`th run
+    let bounds = __run_bounds(nums.len(), __run_count(nums.len(), parallel));
+    if cancel.is_some_and(|cancel| cancel()) {
+        return false;
+    }
+    __sort_partitions(nums, &bounds, cmp, pool, run_sort);
+    __merge_runs(nums, scratch, bounds, cmp, pool, cancel, run_sort)
+}
+
+// Sorts the runs of `nums` between `bounds`, each as a job of its own.
+fn __sort_partitions<T, F>(
+    nums: &mut [T],
+    bounds: &[usize],
+    cmp: &F,
+    pool: &ThreadPool,
+    run_sort: RunSort<'_>,
+) where
+    T: Send + Sync + Copy,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let runs = bounds.len() - 1;
+    pool.scope(|s| {
+        let mut rest = nums;
+        for (i, run) in bounds.windows(2).enumerate() {
+            let (run, tail) = mem::take(&mut rest).split_at_mut(run[1] - run[0]);
+            rest = tail;
+            let context = move || format!("sorting partition {} of {}", i, runs);
+            s.spawn(move || {
+                pool::with_context(context, || match run_sort {
+                    RunSort::Unstable => run.sort_unstable_by(cmp),
+                    RunSort::Stable => run.sort_by(cmp),
+                    RunSort::CountInversions(inversions) => {
+                        let count = __count_sort(run, cmp);
+                        inversions.fetch_add(count, atomic::Ordering::Relaxed);
+                    }
+                })
+            });
+        }
+    });
+}
+
+// Merges the sorted runs of `nums` between `bounds` pass by pass, see `__sort_runs`.
+fn __merge_runs<T, F>(
+    nums: &mut [T],
+    scratch: &mut [T],
+    mut bounds: Vec<usize>,
+    cmp: &F,
+    pool: &ThreadPool,
+    cancel: Option<&dyn Fn() -> bool>,
+    run_sort: RunSort<'_>,
+) -> bool
+where
+    T: Send + Sync + Copy,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let inversions = match run_sort {
+        RunSort::CountInversions(inversions) => Some(inversions),
+        RunSort::Unstable | RunSort::Stable => None,
+    };
+    let len = nums.len();
+    let mut in_scratch = false;
+    let mut completed = true;
+    while bounds.len() > 2 {
+        if cancel.is_some_and(|cancel| cancel()) {
+            completed = false;
+            break;
+        }
+        if in_scratch {
+            __merge_pass(scratch, nums, &bounds, cmp, pool, inversions);
+        } else {
+            __merge_pass(nums, scratch, &bounds, cmp, pool, inversions);
+        }
+        bounds = bounds.iter().step_by(2).copied().collect();
+        if bounds.last() != Some(&len) {
+            bounds.push(len);
+        }
+        in_scratch = !in_scratch;
+    }
+    if in_scratch {
+        nums.copy_from_slice(scratch);
+    }
+    completed
+}
+
+// The number of runs `len` elements are split into for `parallel` threads. Every run
+// gets at least one element: with fewer elements than threads, each element is a run of
+// its own and the surplus threads stay idle, and an empty input is a single empty run.
+fn __run_count(len: usize, parallel: usize) -> usize {
+    match (len, parallel) {
+        (0, _) | (_, 0) => 1,
+        (len, parallel) if len <= parallel => len,
+        (_, parallel) => parallel,
+    }
+}
+
+// The starts of `runs` runs of lengths differing by at most one, followed by `len`, so
+// that `bounds[i]..bounds[i + 1]` is the `i`th run. The products are taken in `u128`,
+// where `i * len` cannot overflow however long the input.
+fn __run_bounds(len: usize, runs: usize) -> Vec<usize> {
+    (0..=runs)
+        .map(|i| (i as u128 * len as u128 / runs as u128) as usize)
+        .collect()
+}
+
+// Merges the sorted runs of `src` between `bounds` pairwise into `dst`, adding the
+// inversions between the merged runs to `inversions`. An odd run out is merged with
+// nothing, that is copied, and waits for the next pass.
+fn __merge_pass<T, F>(
+    src: &[T],
+    dst: &mut [T],
+    bounds: &[usize],
+    cmp: &F,
+    pool: &ThreadPool,
+    inversions: Option<&AtomicU64>,
+) where
+    T: Send + Sync + Copy,
+    F: Fn(&T, &T) -> Ordering + Sync,
+{
+    let runs = bounds.len() - 1;
+    pool.scope(|s| {
+        let mut rest = dst;
+        for i in (0..runs).step_by(2) {
+            let (lo, mid, hi) = (bounds[i], bounds[i + 1], bounds[(i + 2).min(runs)]);
+            let (to, tail) = mem::take(&mut rest).split_at_mut(hi - lo);
+            rest = tail;
+            let (left, right) = (&src[lo..mid], &src[mid..hi]);
+            let context = move || format!("merging elements {}..{} and {}..{}", lo, mid, mid, hi);
+            s.spawn(move || {
+                let count = pool::with_context(context, || __merge(left, right, to, cmp));
+                if let Some(inversions) = inversions {
+                    inversions.fetch_add(count, atomic::Ordering::Relaxed);
+                }
+            });
+        }
+    });
+}
+
+// Merges the sorted `left` and `right` into `to`, taking from `left` on ties, and
+// returns the number of inversions between them: every element taken from `right`
+// jumps over the rest of `left`.
+fn __merge<T, F>(left: &[T], right: &[T], to: &mut [T], cmp: &F) -> u64
+where
+    T: Copy,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let (mut l, mut r) = (0, 0);
+    let mut inversions = 0;
+    for slot in to.iter_mut() {
+        if r == right.len() || (l < left.len() && cmp(&left[l], &right[r]) != Ordering::Greater) {
+            *slot = left[l];
+            l += 1;
+        } else {
+            *slot = right[r];
+            r += 1;
+            inversions += (left.len() - l) as u64;
+        }
+    }
+    inversions
+}
+
+// Merges the sorted `nums[..mid]` and `nums[mid..]` in place, with `buf` holding at
+// most `cap` elements. A side that fits into `buf` is copied out and merged back from
+// the other end; otherwise the longer side is cut in half, the other one at the matching
+// position, and the two inner pieces are rotated past each other, leaving two smaller
+// merges (the merge without buffer of the C++ standard library).
+fn __merge_bounded<T, F>(nums: &mut [T], mid: usize, buf: &mut Vec<T>, cap: usize, cmp: &F)
+where
+    T: Copy,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let (l, r) = (mid, nums.len() - mid);
+    if l == 0 || r == 0 {
+        return;
+    }
+    if l + r == 2 {
+        if cmp(&nums[0], &nums[1]) == Ordering::Greater {
+            nums.swap(0, 1);
+        }
+        return;
+    }
+    if l <= cap.min(r) {
+        buf.clear();
+        buf.extend_from_slice(&nums[..mid]);
+        let (mut i, mut j) = (0, mid);
+        for k in 0..nums.len() {
+            if i == l {
+                break;
             }
-            for handle in handles {
-                handle.join().unwrap();
+            if j < nums.len() && cmp(&nums[j], &buf[i]) == Ordering::Less {
+                nums[k] = nums[j];
+                j += 1;
+            } else {
+                nums[k] = buf[i];
+                i += 1;
             }
         }
-    });
-    nums.truncate(origin_len);
+        return;
+    }
+    if r <= cap {
+        buf.clear();
+        buf.extend_from_slice(&nums[mid..]);
+        let (mut i, mut j) = (mid, r);
+        for k in (0..nums.len()).rev() {
+            if j == 0 {
+                break;
+            }
+            if i > 0 && cmp(&buf[j - 1], &nums[i - 1]) == Ordering::Less {
+                nums[k] = nums[i - 1];
+                i -= 1;
+            } else {
+                nums[k] = buf[j - 1];
+                j -= 1;
+            }
+        }
+        return;
+    }
+    let (cut1, cut2) = if l > r {
+        let cut1 = l / 2;
+        let pivot = nums[cut1];
+        (
+            cut1,
+            mid + nums[mid..].partition_point(|x| cmp(x, &pivot) == Ordering::Less),
+        )
+    } else {
+        let cut2 = mid + r / 2;
+        let pivot = nums[cut2];
+        (
+            nums[..mid].partition_point(|x| cmp(x, &pivot) != Ordering::Greater),
+            cut2,
+        )
+    };
+    nums[cut1..cut2].rotate_left(mid - cut1);
+    let new_mid = cut1 + (cut2 - mid);
+    let (lo, hi) = nums.split_at_mut(new_mid);
+    __merge_bounded(lo, cut1, buf, cap, cmp);
+    __merge_bounded(hi, cut2 - new_mid, buf, cap, cmp);
+}
+
+// Sorts `nums` with a serial merge sort and returns the number of inversions it had.
+// Short slices use insertion sort, whose every shift undoes exactly one inversion.
+fn __count_sort<T, F>(nums: &mut [T], cmp: &F) -> u64
+where
+    T: Copy,
+    F: Fn(&T, &T) -> Ordering,
+{
+    let len = nums.len();
+    if len <= 32 {
+        let mut inversions = 0;
+        for i in 1..len {
+            let mut j = i;
+            while j > 0 && cmp(&nums[j - 1], &nums[j]) == Ordering::Greater {
+                nums.swap(j - 1, j);
+                j -= 1;
+            }
+            inversions += (i - j) as u64;
+        }
+        return inversions;
+    }
+    let mid = len / 2;
+    let inversions = __count_sort(&mut nums[..mid], cmp) + __count_sort(&mut nums[mid..], cmp);
+    let left = nums[..mid].to_vec();
+    let right = nums[mid..].to_vec();
+    inversions + __merge(&left, &right, nums, cmp)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{gen_few_unique, gen_sorted, gen_tagged, tags, Tagged};
 
     #[test]
     fn test_parallel_sort() {
@@ -139,4 +1452,718 @@ mod tests {
         parallel_sort(&mut nums, parallel);
         assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
     }
+
+    #[test]
+    fn test_parallel_sort_large_input() {
+        for parallel in [1, 2, 200, 255] {
+            let mut nums = gen_few_unique(1000, 1000);
+            let mut expected = nums.clone();
+            expected.sort();
+            parallel_sort(&mut nums, parallel);
+            assert!(
+                nums == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_parallel_sort() {
+        let mut nums = vec![4.0, 2.0, 7.0, 1.0, 5.0, 3.0, 6.0];
+        assert_eq!(try_parallel_sort(&mut nums, 2), Ok(()));
+        assert_eq!(nums, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn test_try_parallel_sort_nan() {
+        let mut nums = vec![4.0, 2.0, 7.0, 1.0, f64::NAN, 3.0, f64::NAN];
+        let parallel = 4;
+        assert_eq!(
+            try_parallel_sort(&mut nums, parallel),
+            Err(SortError::Incomparable { index: 4 })
+        );
+
+        // the scan runs in as many chunks as the sort has runs, however large `parallel`
+        for parallel in [0, 1, 3, 1000, usize::MAX] {
+            let mut nums: Vec<f64> = gen_few_unique(1000, 100)
+                .into_iter()
+                .map(f64::from)
+                .collect();
+            nums[700] = f64::NAN;
+            nums[900] = f64::NAN;
+            assert_eq!(
+                try_parallel_sort(&mut nums, parallel),
+                Err(SortError::Incomparable { index: 700 }),
+                "wrong index for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_slice() {
+        let mut nums = [4, 2, 7, 1, 5, 3, 6, 4, 2, 7];
+        parallel_sort_slice(&mut nums[2..], 2);
+        assert_eq!(nums, [4, 2, 1, 2, 3, 4, 5, 6, 7, 7]);
+    }
+
+    #[test]
+    fn test_parallel_sort_slice_power_of_two() {
+        let mut nums = [4, 2, 7, 1, 5, 3, 6, 8];
+        parallel_sort_slice(&mut nums, 4);
+        assert_eq!(nums, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_parallel_sort_auto() {
+        let mut nums = gen_few_unique(1000, 1000);
+        let mut expected = nums.clone();
+        expected.sort();
+        parallel_sort_auto(&mut nums);
+        assert_eq!(nums, expected);
+    }
+
+    #[test]
+    fn test_parallel_sort_with_pool_reused() {
+        let pool = ThreadPool::new(3);
+        for len in [0, 1, 7, 64, 100] {
+            let mut nums = gen_few_unique(len, 50);
+            let mut expected = nums.clone();
+            expected.sort();
+            parallel_sort_with_pool(&mut nums, 4, &pool);
+            assert_eq!(nums, expected);
+        }
+    }
+
+    #[test]
+    fn test_parallel_sorted() {
+        let nums = [4, 2, 7, 1, 5, 3, 6];
+        assert_eq!(parallel_sorted(&nums, 2), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(nums, [4, 2, 7, 1, 5, 3, 6]);
+    }
+
+    #[test]
+    fn test_parallel_sort_nan() {
+        let nums: Vec<f64> = gen_few_unique(1000, 100)
+            .into_iter()
+            .map(|x| match x {
+                0 => f64::NAN,
+                1 => -f64::NAN,
+                x => x as f64 - 50.0,
+            })
+            .collect();
+        let nans = nums.iter().filter(|x| x.is_nan()).count();
+        let mut expected: Vec<f64> = nums.iter().copied().filter(|x| !x.is_nan()).collect();
+        expected.sort_by(f64::total_cmp);
+        for parallel in [1, 3, 8] {
+            let mut first = nums.clone();
+            assert_eq!(
+                parallel_sort_nan(&mut first, parallel, NanPolicy::First),
+                Ok(())
+            );
+            assert!(first[..nans].iter().all(|x| x.is_nan()));
+            assert!(first[nans..] == expected[..], "first with {}", parallel);
+
+            let mut last = nums.clone();
+            assert_eq!(
+                parallel_sort_nan(&mut last, parallel, NanPolicy::Last),
+                Ok(())
+            );
+            assert!(
+                last[..1000 - nans] == expected[..],
+                "last with {}",
+                parallel
+            );
+            assert!(last[1000 - nans..].iter().all(|x| x.is_nan()));
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_nan_error() {
+        let mut nums = vec![1.0, 0.0, -0.0, f64::NAN, 2.0, f64::NAN];
+        let result = parallel_sort_nan(&mut nums, 4, NanPolicy::Error);
+        assert_eq!(result, Err(SortError::Incomparable { index: 3 }));
+        assert_eq!(nums[..3], [1.0, 0.0, -0.0]);
+
+        let mut nums = vec![1.0, 0.0, -0.0, 2.0];
+        assert_eq!(parallel_sort_nan(&mut nums, 4, NanPolicy::Error), Ok(()));
+        assert_eq!(nums, vec![-0.0, 0.0, 1.0, 2.0]);
+        assert!(nums[0].is_sign_negative() && nums[1].is_sign_positive());
+    }
+
+    #[test]
+    fn test_parallel_sort_boxed() {
+        let nums = gen_few_unique(1000, 1000);
+        let mut expected = nums.clone();
+        expected.sort();
+        let sorted = parallel_sort_boxed(nums, 4);
+        assert_eq!(sorted.len(), 1000);
+        assert_eq!(*sorted, expected[..]);
+    }
+
+    #[test]
+    fn test_parallel_sort_timeout() {
+        let nums = gen_few_unique(1000, 1000);
+        let mut expected = nums.clone();
+        expected.sort();
+        let mut sorted = nums.clone();
+        let budget = Duration::from_secs(600);
+        assert_eq!(parallel_sort_timeout(&mut sorted, 4, budget), Ok(()));
+        assert_eq!(sorted, expected);
+
+        // the budget is spent before the first check, so the input is left as it is
+        let mut timed_out = nums.clone();
+        assert_eq!(
+            parallel_sort_timeout(&mut timed_out, 4, Duration::ZERO),
+            Err(SortError::TimedOut)
+        );
+        assert_eq!(timed_out, nums);
+    }
+
+    #[test]
+    fn test_parallel_sort_cancellable() {
+        let nums = gen_few_unique(1000, 1000);
+        let mut expected = nums.clone();
+        expected.sort();
+        let mut sorted = nums.clone();
+        let cancel = AtomicBool::new(false);
+        assert_eq!(parallel_sort_cancellable(&mut sorted, 4, &cancel), Ok(()));
+        assert_eq!(sorted, expected);
+
+        let mut cancelled = nums.clone();
+        cancel.store(true, atomic::Ordering::Relaxed);
+        assert_eq!(
+            parallel_sort_cancellable(&mut cancelled, 4, &cancel),
+            Err(SortError::Cancelled)
+        );
+        assert_eq!(cancelled, nums);
+    }
+
+    #[test]
+    fn test_parallel_sort_ord() {
+        let mut nums: Vec<u64> = gen_few_unique(1000, 1000)
+            .into_iter()
+            .map(u64::from)
+            .collect();
+        let mut expected = nums.clone();
+        expected.sort();
+        parallel_sort_ord(&mut nums, 4);
+        assert_eq!(nums, expected);
+    }
+
+    #[test]
+    fn test_parallel_sort_parallel_values() {
+        for parallel in [0, 1, 3, 255] {
+            let mut nums = gen_few_unique(1000, 1000);
+            let mut expected = nums.clone();
+            expected.sort();
+            parallel_sort(&mut nums, parallel);
+            assert!(
+                nums == expected,
+                "unsorted output for parallel = {}",
+                parallel
+            );
+        }
+    }
+
+    #[test]
+    fn test_stable_parallel_sort() {
+        // partitions of unequal lengths, and odd runs out waiting for the next pass
+        for len in [0, 1, 7, 1000, 1023] {
+            for parallel in [0, 1, 3, 4, 16] {
+                let mut nums = gen_tagged(len, 10);
+                let mut expected = nums.clone();
+                expected.sort_by_key(|record| record.0);
+                stable_parallel_sort(&mut nums, parallel);
+                assert!(
+                    tags(&nums) == tags(&expected),
+                    "unstable output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_with_scratch() {
+        let mut scratch = vec![0; 1000];
+        for len in [0, 1, 7, 513, 1000] {
+            for parallel in [1, 4, 16] {
+                let mut nums = gen_few_unique(len, 1000);
+                let mut expected = nums.clone();
+                expected.sort();
+                parallel_sort_with_scratch(&mut nums, &mut scratch[..len], parallel);
+                assert!(
+                    nums == expected,
+                    "unsorted output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fewer_elements_than_threads() {
+        for (len, parallel) in [(2, 4), (1, 8), (0, 8), (3, 3), (4, 4)] {
+            let nums: Vec<u32> = (0..len as u32).rev().collect();
+            let expected: Vec<u32> = (0..len as u32).collect();
+            let mut sorted = nums.clone();
+            parallel_sort(&mut sorted, parallel);
+            assert!(sorted == expected, "unsorted output for len = {}", len);
+            let mut sorted = nums.clone();
+            parallel_sort_slice(&mut sorted, parallel);
+            assert!(sorted == expected, "unsorted slice for len = {}", len);
+            let mut sorted = nums.clone();
+            parallel_sort_bounded(&mut sorted, parallel, 0);
+            assert!(sorted == expected, "unsorted bounded for len = {}", len);
+            let mut dst = vec![0; len];
+            parallel_sort_to(&nums, &mut dst, parallel);
+            assert!(dst == expected, "unsorted destination for len = {}", len);
+            let mut sorted = nums.clone();
+            let inversions = parallel_sort_count_inversions(&mut sorted, parallel);
+            assert_eq!(inversions, (len * len.saturating_sub(1) / 2) as u64);
+        }
+    }
+
+    #[test]
+    fn test_run_count() {
+        assert_eq!(__run_count(0, 0), 1);
+        assert_eq!(__run_count(0, 8), 1);
+        assert_eq!(__run_count(1, 8), 1);
+        assert_eq!(__run_count(2, 4), 2);
+        assert_eq!(__run_count(4, 4), 4);
+        assert_eq!(__run_count(100, 6), 6);
+        assert_eq!(__run_count(100, 0), 1);
+        assert_eq!(__run_count(usize::MAX, usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn test_parallel_sort_by_keys() {
+        for len in [0, 1, 7, 513, 1000] {
+            for parallel in [1, 3, 16] {
+                let mut keys: Vec<u64> = gen_few_unique(len, 100)
+                    .into_iter()
+                    .map(u64::from)
+                    .collect();
+                // records that are not `Copy` and carry their key and input index
+                let mut data: Vec<String> = keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, key)| format!("{:03}-{:04}", key, i))
+                    .collect();
+                let mut expected = data.clone();
+                expected.sort();
+                parallel_sort_by_keys(&mut data, &mut keys, parallel);
+                assert!(
+                    data == expected,
+                    "unsorted output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+                assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]));
+                assert!(data
+                    .iter()
+                    .zip(&keys)
+                    .all(|(x, key)| x[..3] == format!("{:03}", key)));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "there must be one key per element")]
+    fn test_parallel_sort_by_too_few_keys() {
+        parallel_sort_by_keys(&mut [3, 1, 2], &mut [0; 2], 2);
+    }
+
+    #[test]
+    fn test_parallel_sort_reverse_wrapper() {
+        use std::cmp::Reverse;
+
+        // the largest `Reverse` is the smallest wrapped value, which must end up at the back
+        for len in [1, 5, 7, 100, 1000] {
+            let nums: Vec<i32> = gen_few_unique(len, 200)
+                .into_iter()
+                .map(|x| x as i32 - 100)
+                .collect();
+            let mut reversed: Vec<Reverse<i32>> = nums.iter().copied().map(Reverse).collect();
+            parallel_sort(&mut reversed, 4);
+            let mut expected = nums;
+            expected.sort_by(|a, b| b.cmp(a));
+            let unwrapped: Vec<i32> = reversed.into_iter().map(|x| x.0).collect();
+            assert!(unwrapped == expected, "unsorted output for len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_to() {
+        for len in [0, 1, 7, 513, 1000] {
+            for parallel in [1, 3, 16] {
+                let src = gen_few_unique(len, 1000);
+                let original = src.clone();
+                let mut expected = src.clone();
+                expected.sort();
+                let mut dst = vec![u32::MAX; len];
+                parallel_sort_to(&src, &mut dst, parallel);
+                assert!(
+                    dst == expected,
+                    "unsorted output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+                assert!(src == original, "source modified for len = {}", len);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "destination must be as long as the source")]
+    fn test_parallel_sort_to_short_destination() {
+        parallel_sort_to(&[3, 1, 2], &mut [0; 2], 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "scratch must be as long as the input")]
+    fn test_parallel_sort_with_short_scratch() {
+        parallel_sort_with_scratch(&mut [3, 1, 2], &mut [0; 2], 2);
+    }
+
+    #[test]
+    fn test_parallel_sort_count_inversions() {
+        for len in [0, 1, 2, 31, 33, 100, 1000] {
+            let nums = gen_few_unique(len, 50);
+            let mut expected = 0;
+            for i in 0..len {
+                for j in i + 1..len {
+                    expected += (nums[i] > nums[j]) as u64;
+                }
+            }
+            let mut sorted_nums = nums.clone();
+            sorted_nums.sort();
+            for parallel in [1, 3, 8] {
+                let mut sorted = nums.clone();
+                let inversions = parallel_sort_count_inversions(&mut sorted, parallel);
+                assert!(
+                    inversions == expected,
+                    "length {} parallel {}",
+                    len,
+                    parallel
+                );
+                assert!(
+                    sorted == sorted_nums,
+                    "length {} parallel {}",
+                    len,
+                    parallel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_count_inversions_reversed() {
+        let mut nums: Vec<u64> = (0..5000).rev().collect();
+        assert_eq!(
+            parallel_sort_count_inversions(&mut nums, 4),
+            5000 * 4999 / 2
+        );
+        assert_eq!(parallel_sort_count_inversions(&mut nums, 4), 0);
+    }
+
+    #[test]
+    fn test_parallel_sort_uneven_partitions() {
+        // every count of partitions, including odd ones that leave a run out of a pass
+        for parallel in 1..=12 {
+            for len in [5, 11, 1000] {
+                let mut nums = gen_few_unique(len, 1000);
+                let mut expected = nums.clone();
+                expected.sort();
+                parallel_sort(&mut nums, parallel);
+                assert!(nums == expected, "length {} parallel {}", len, parallel);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_bounded() {
+        for len in [0, 1, 2, 7, 100, 1000, 4099] {
+            let nums = gen_few_unique(len, 500);
+            let mut expected = nums.clone();
+            expected.sort();
+            for parallel in [1, 3, 8] {
+                for max_scratch_bytes in [0, 3, 64, 4096, usize::MAX] {
+                    let mut sorted = nums.clone();
+                    parallel_sort_bounded(&mut sorted, parallel, max_scratch_bytes);
+                    assert!(
+                        sorted == expected,
+                        "length {} parallel {} budget {}",
+                        len,
+                        parallel,
+                        max_scratch_bytes
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_bounded_respects_cap() {
+        let mut nums: Vec<u32> = (0..500)
+            .map(|x| x * 2)
+            .chain((0..300).map(|x| x * 3))
+            .collect();
+        let mut expected = nums.clone();
+        expected.sort();
+        let mut buf = Vec::with_capacity(16);
+        __merge_bounded(&mut nums, 500, &mut buf, 16, &u32::cmp);
+        assert_eq!(nums, expected);
+        assert!(
+            buf.capacity() == 16,
+            "the buffer grew to {}",
+            buf.capacity()
+        );
+    }
+
+    #[test]
+    fn test_run_bounds_large_len() {
+        for runs in [1, 3, 8, 1000] {
+            let bounds = __run_bounds(usize::MAX, runs);
+            assert_eq!(bounds.len(), runs + 1);
+            assert_eq!(bounds[0], 0);
+            assert_eq!(bounds[runs], usize::MAX);
+            let lens: Vec<usize> = bounds.windows(2).map(|run| run[1] - run[0]).collect();
+            assert!(lens.iter().max().unwrap() - lens.iter().min().unwrap() <= 1);
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_refs() {
+        let words: Vec<String> = gen_few_unique(37, 50)
+            .iter()
+            .map(|x| format!("{:02}", x))
+            .collect();
+        let mut refs: Vec<&String> = words.iter().collect();
+        // every reference survives the merges exactly once
+        parallel_sort(&mut refs, 4);
+        let mut expected = words.clone();
+        expected.sort();
+        assert_eq!(refs, expected.iter().collect::<Vec<_>>());
+        assert!(refs
+            .iter()
+            .all(|r| words.iter().any(|w| std::ptr::eq(*r, w))));
+    }
+
+    #[test]
+    fn test_parallel_sort_keeps_capacity() {
+        for len in [0, 1, 2, 5, 8, 100, 1000] {
+            let mut nums = gen_few_unique(len, 100);
+            nums.shrink_to_fit();
+            let mut expected = nums.clone();
+            expected.sort();
+            let before = (nums.as_ptr(), nums.capacity());
+            parallel_sort(&mut nums, 4);
+            assert!(nums == expected, "unsorted output for len = {}", len);
+            assert!(
+                (nums.as_ptr(), nums.capacity()) == before,
+                "reallocated for len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_sort_dedup() {
+        for len in [0, 1, 2, 3, 10, 100, 1000, 4097] {
+            for parallel in [0, 1, 2, 3, 8] {
+                let mut nums = gen_few_unique(len, 64);
+                let mut expected = nums.clone();
+                expected.sort();
+                expected.dedup();
+                assert_eq!(sort_dedup(&mut nums, parallel), expected.len());
+                assert!(
+                    nums == expected,
+                    "wrong output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_tuned() {
+        for len in [0, 1, 7, 100, 1000, 1025] {
+            for (partitions, merge_threads) in [(0, 0), (1, 4), (4, 4), (16, 1), (16, 3), (3, 16)] {
+                let mut nums = gen_few_unique(len, 500);
+                let mut expected = nums.clone();
+                expected.sort();
+                parallel_sort_tuned(&mut nums, partitions, merge_threads);
+                assert!(
+                    nums == expected,
+                    "unsorted output for len = {}, partitions = {}, merge_threads = {}",
+                    len,
+                    partitions,
+                    merge_threads
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_total_f64() {
+        let specials = [
+            0.0,
+            -0.0,
+            f64::NAN,
+            -f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MIN_POSITIVE,
+            f64::MIN_POSITIVE / 4.0,
+            -f64::MIN_POSITIVE / 4.0,
+            f64::from_bits(1),
+        ];
+        for len in [0, 1, 7, 100, 1000] {
+            for parallel in [0, 1, 3, 4] {
+                let mut nums: Vec<f64> = gen_few_unique(len, 20)
+                    .into_iter()
+                    .map(|x| match x {
+                        k if (k as usize) < specials.len() => specials[k as usize],
+                        k => k as f64 - 15.0,
+                    })
+                    .collect();
+                let mut expected = nums.clone();
+                expected.sort_by(f64::total_cmp);
+                parallel_sort_total_f64(&mut nums, parallel);
+                let bits = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+                assert!(
+                    bits(&nums) == bits(&expected),
+                    "wrong output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_sort_stats() {
+        for len in [0, 1, 7, 1000] {
+            for (parallel, passes) in [(0, 0), (1, 0), (2, 1), (3, 2), (4, 2), (5, 3), (16, 4)] {
+                let mut nums = gen_few_unique(len, 500);
+                let mut expected = nums.clone();
+                expected.sort();
+                let stats = parallel_sort_stats(&mut nums, parallel);
+                assert!(
+                    nums == expected,
+                    "unsorted output for len = {}, parallel = {}",
+                    len,
+                    parallel
+                );
+                // fewer elements than threads make fewer partitions
+                let runs = __run_count(len, parallel);
+                assert_eq!(
+                    stats.passes,
+                    passes.min(runs.next_power_of_two().ilog2() as usize)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancelled_sort_keeps_elements() {
+        // records equal to the maximum without being identical to it must all survive
+        for len in [5, 7, 100, 1000] {
+            for stop in 1..4 {
+                let nums = gen_tagged(len, 3);
+                let mut sorted = nums.clone();
+                let checks = AtomicUsize::new(0);
+                let cancel = || checks.fetch_add(1, atomic::Ordering::Relaxed) >= stop;
+                let cmp = |x: &Tagged, y: &Tagged| x.cmp(y);
+                __sort(&mut sorted, 4, &cmp, &__pool(len, 4), Some(&cancel));
+                let mut tags = tags(&sorted);
+                tags.sort();
+                assert!(
+                    tags == gen_sorted(len),
+                    "lost elements for len = {}, stop = {}",
+                    len,
+                    stop
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_huge_parallel_small_input() {
+        // the pools are sized by the partitions, not by `parallel`, or these would try to
+        // start billions of threads
+        for parallel in [100_000, usize::MAX] {
+            let mut nums = vec![2, 1];
+            parallel_sort(&mut nums, parallel);
+            assert_eq!(nums, vec![1, 2]);
+            let mut nums = vec![2, 1];
+            parallel_sort_slice(&mut nums, parallel);
+            assert_eq!(nums, vec![1, 2]);
+            let mut nums = vec![2, 1];
+            stable_parallel_sort(&mut nums, parallel);
+            assert_eq!(nums, vec![1, 2]);
+            let mut nums = vec![2, 1];
+            assert_eq!(parallel_sort_count_inversions(&mut nums, parallel), 1);
+            let mut nums = vec![2, 1, 2];
+            assert_eq!(sort_dedup(&mut nums, parallel), 2);
+            let mut nums = vec![2.0, 1.0];
+            assert_eq!(try_parallel_sort(&mut nums, parallel), Ok(()));
+            let mut nums = vec![2, 1];
+            parallel_sort_tuned(&mut nums, parallel, parallel);
+            assert_eq!(nums, vec![1, 2]);
+            let mut nums = vec![2, 1];
+            parallel_sort_bounded(&mut nums, parallel, 0);
+            assert_eq!(nums, vec![1, 2]);
+            let mut nums = vec![2, 1];
+            assert_eq!(parallel_sort_stats(&mut nums, parallel).passes, 1);
+        }
+    }
+
+    #[test]
+    fn test_try_parallel_sort_incomparable_pair() {
+        // ordered by divisibility, so every element compares with itself but 3 and 4 do
+        // not compare with each other
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Divisor(u32);
+        impl PartialOrd for Divisor {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                match (self.0, other.0) {
+                    (x, y) if x == y => Some(Ordering::Equal),
+                    (x, y) if y % x == 0 => Some(Ordering::Less),
+                    (x, y) if x % y == 0 => Some(Ordering::Greater),
+                    _ => None,
+                }
+            }
+        }
+        let mut nums = vec![Divisor(4), Divisor(1), Divisor(2), Divisor(8)];
+        assert_eq!(try_parallel_sort(&mut nums, 2), Ok(()));
+        assert_eq!(nums, vec![Divisor(1), Divisor(2), Divisor(4), Divisor(8)]);
+
+        for parallel in [1, 2, 4] {
+            // 3 compares with neither 2 nor 4
+            let mut nums = vec![Divisor(2), Divisor(1), Divisor(3), Divisor(1), Divisor(4)];
+            let result = try_parallel_sort(&mut nums, parallel);
+            let Err(SortError::Incomparable { index }) = result else {
+                panic!("{:?} for parallel = {}", result, parallel);
+            };
+            // the sort stopped with every element still there, and the reported one has
+            // an element it does not compare with
+            let mut kept: Vec<u32> = nums.iter().map(|x| x.0).collect();
+            kept.sort();
+            assert_eq!(kept, [1, 1, 2, 3, 4]);
+            assert!(
+                nums.iter().any(|x| x.partial_cmp(&nums[index]).is_none()),
+                "{:?} at {} for parallel = {}",
+                nums,
+                index,
+                parallel
+            );
+        }
+    }
 }
@@ -0,0 +1,315 @@
+//! This module provides a small reusable thread pool for the parallel sorts.
+//!
+//! Spawning fresh threads on every call dominates the cost of sorting many small inputs.
+//! A `ThreadPool` creates its worker threads once; the `*_with_pool` sort functions then
+//! run their work on those workers through a borrowed pool.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::bitonic_parallel::bitonic_sort_with_pool;
+//! use bitonic_sort::pool::ThreadPool;
+//!
+//! let pool = ThreadPool::new(3);
+//! for _ in 0..10 {
+//!     let mut nums = vec![4, 2, 7, 1, 5, 3, 6];
+//!     bitonic_sort_with_pool(&mut nums, 4, &pool);
+//!     assert_eq!(nums, vec![1, 2, 3, 4, 5, 6, 7]);
+//! }
+//! ```
+use std::any::Any;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    shutdown: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    signal: Condvar,
+}
+
+/// A fixed set of worker threads that run the jobs of scoped sorts.
+///
+/// A thread waiting for a scope to finish executes queued jobs itself, so nested scopes
+/// (as used by the recursive bitonic sort) never deadlock, and a pool of `n` workers
+/// together with the calling thread gives `n + 1` threads of parallelism. A pool with no
+/// workers runs everything on the calling thread.
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// Creates a pool with `threads` worker threads.
+    pub fn new(threads: usize) -> ThreadPool {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                jobs: VecDeque::new(),
+                shutdown: false,
+            }),
+            signal: Condvar::new(),
+        });
+        let workers = (0..threads)
+            .map(|i| {
+                let shared = shared.clone();
+                // named, so that a panic message tells a sort's worker from other threads
+                let builder = thread::Builder::new().name(format!("bitonic-sort-{}", i));
+                let worker = builder.spawn(move || loop {
+                    let job = {
+                        let mut queue = lock(&shared.queue);
+                        loop {
+                            if let Some(job) = queue.jobs.pop_front() {
+                                break job;
+                            }
+                            if queue.shutdown {
+                                return;
+                            }
+                            queue = shared.signal.wait(queue).unwrap_or_else(|e| e.into_inner());
+                        }
+                    };
+                    job();
+                });
+                worker.expect("failed to spawn a worker thread")
+            })
+            .collect();
+        ThreadPool { shared, workers }
+    }
+
+    // The pool behind the per-call sorts: the calling thread works too, so `parallel`
+    // rounded up to a power of two needs one worker fewer.
+    pub(crate) fn for_parallel(parallel: usize) -> ThreadPool {
+        ThreadPool::new(parallel.next_power_of_two() - 1)
+    }
+
+    /// Returns the number of worker threads in the pool.
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs `f` with a [`Scope`] whose jobs may borrow from the enclosing stack frame.
+    ///
+    /// Returns once every job spawned on the scope has finished. If a job panicked, the
+    /// panic is resumed on the calling thread.
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Scope<'_, 'env>) -> R,
+    {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                pending: Mutex::new(0),
+                done: Condvar::new(),
+                panic: Mutex::new(None),
+            }),
+            _env: PhantomData,
+        };
+        // wait even when `f` unwinds, the jobs may still borrow its stack frame
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.wait();
+        let job_panic = lock(&scope.state.panic).take();
+        match (result, job_panic) {
+            (Err(payload), _) | (Ok(_), Some(payload)) => panic::resume_unwind(payload),
+            (Ok(result), None) => result,
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        lock(&self.shared.queue).shutdown = true;
+        self.shared.signal.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+struct ScopeState {
+    pending: Mutex<usize>,
+    done: Condvar,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// A handle for spawning jobs that borrow data living for `'env`, see [`ThreadPool::scope`].
+pub struct Scope<'pool, 'env> {
+    pool: &'pool ThreadPool,
+    state: Arc<ScopeState>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> Scope<'_, 'env> {
+    /// Queues `f` to run on the pool.
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'env,
+    {
+        let state = self.state.clone();
+        *lock(&state.pending) += 1;
+        let job: Box<dyn FnOnce() + Send + 'env> = Box::new(move || {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+                *lock(&state.panic) = Some(payload);
+            }
+            let mut pending = lock(&state.pending);
+            *pending -= 1;
+            if *pending == 0 {
+                state.done.notify_all();
+            }
+        });
+        // SAFETY: `ThreadPool::scope` does not return before `pending` drops back to zero,
+        // so the job never outlives the `'env` data it borrows.
+        let job: Job = unsafe { mem::transmute(job) };
+        lock(&self.pool.shared.queue).jobs.push_back(job);
+        self.pool.shared.signal.notify_one();
+    }
+
+    fn wait(&self) {
+        // help with queued jobs first. Once the queue is empty, the remaining jobs of this
+        // scope are running on other threads, and any job queued later is picked up by
+        // the thread that queued it while it waits for its own scope.
+        while *lock(&self.state.pending) > 0 {
+            let job = lock(&self.pool.shared.queue).jobs.pop_front();
+            match job {
+                Some(job) => job(),
+                None => break,
+            }
+        }
+        let mut pending = lock(&self.state.pending);
+        while *pending > 0 {
+            pending = self
+                .state
+                .done
+                .wait(pending)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+// Runs `f`, and if it panics, panics again with `context()` in front of the original
+// message. The jobs of the sorts use it to say which part of the input they were working
+// on when the panic is resumed on the calling thread.
+pub(crate) fn with_context<R>(context: impl FnOnce() -> String, f: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => panic!("{}: {}", context(), panic_message(&*payload)),
+    }
+}
+
+// The message of a panic payload, which is a `&str` or a `String` for every `panic!`
+// with a message.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("Box<dyn Any>", String::as_str),
+    }
+}
+
+// Spawns a scoped thread named `name`, for the parts of the crate that run directly on
+// `std::thread::scope` rather than on a pool.
+pub(crate) fn spawn_named<'scope, 'env, F, R>(
+    s: &'scope thread::Scope<'scope, 'env>,
+    name: &str,
+    f: F,
+) -> thread::ScopedJoinHandle<'scope, R>
+where
+    F: FnOnce() -> R + Send + 'scope,
+    R: Send + 'scope,
+{
+    thread::Builder::new()
+        .name(name.to_string())
+        .spawn_scoped(s, f)
+        .expect("failed to spawn a thread")
+}
+
+// Joins a scoped thread, resuming its panic, if any, with the original payload instead
+// of wrapping it in the `Err` of `join`.
+pub(crate) fn join<R>(handle: thread::ScopedJoinHandle<'_, R>) -> R {
+    handle
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+// a job panicking never poisons the pool's state, its panic is caught and stored
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_runs_all_jobs() {
+        let pool = ThreadPool::new(2);
+        let mut nums = vec![0; 16];
+        pool.scope(|s| {
+            for (i, num) in nums.iter_mut().enumerate() {
+                s.spawn(move || *num = i * 2);
+            }
+        });
+        assert_eq!(nums, (0..16).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_scope_nested_without_workers() {
+        fn sum(pool: &ThreadPool, nums: &[u64]) -> u64 {
+            if nums.len() <= 1 {
+                return nums.iter().sum();
+            }
+            let (lo, hi) = nums.split_at(nums.len() / 2);
+            let (mut a, mut b) = (0, 0);
+            pool.scope(|s| {
+                s.spawn(|| a = sum(pool, lo));
+                s.spawn(|| b = sum(pool, hi));
+            });
+            a + b
+        }
+        let nums: Vec<u64> = (1..=100).collect();
+        for threads in [0, 1, 3] {
+            assert_eq!(sum(&ThreadPool::new(threads), &nums), 5050);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "job failed")]
+    fn test_scope_propagates_panic() {
+        let pool = ThreadPool::new(1);
+        pool.scope(|s| s.spawn(|| panic!("job failed")));
+    }
+
+    #[test]
+    fn test_workers_are_named() {
+        let pool = ThreadPool::new(2);
+        let names = Mutex::new(Vec::new());
+        pool.scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    let name = thread::current().name().map(str::to_string);
+                    lock(&names).push(name);
+                });
+            }
+        });
+        for name in lock(&names).iter().flatten() {
+            assert!(name.starts_with("bitonic-sort-") || name.starts_with("pool::tests"));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "sorting partition 3: job failed")]
+    fn test_with_context() {
+        with_context(
+            || format!("sorting partition {}", 3),
+            || panic!("job failed"),
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! This module contains binary searches on the ascending output of the sorts.
+//!
+//! The sorts produce plain sorted slices, so these are thin wrappers around
+//! `partition_point`, returning indices rather than the `Result` of `binary_search`.
+//! [`insert_sorted`] and [`remove_sorted`] keep a sorted vector sorted one element at a
+//! time, without running a network again.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::bitonic_serial::bitonic_sort;
+//! use bitonic_sort::search::equal_range;
+//!
+//! let mut nums = vec![3, 1, 2, 3, 1, 3];
+//! bitonic_sort(&mut nums);
+//! assert_eq!(equal_range(&nums, &3), 3..6);
+//! ```
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Returns the index of the first element of `nums` that is not less than `target`.
+///
+/// `nums` must be sorted in ascending order. If every element is less than `target`,
+/// the result is `nums.len()`, so it is always the position where `target` could be
+/// inserted without breaking the order. Elements that are incomparable to `target`,
+/// such as `NaN`, count as not less.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::search::lower_bound;
+///
+/// let nums = [1, 2, 2, 2, 5];
+/// assert_eq!(lower_bound(&nums, &2), 1);
+/// assert_eq!(lower_bound(&nums, &3), 4);
+/// assert_eq!(lower_bound(&nums, &9), 5);
+/// ```
+pub fn lower_bound<T>(nums: &[T], target: &T) -> usize
+where
+    T: PartialOrd,
+{
+    nums.partition_point(|x| x < target)
+}
+
+/// Returns the index of the first element of `nums` that is greater than `target`.
+///
+/// `nums` must be sorted in ascending order. If no element is greater than `target`,
+/// the result is `nums.len()`, so it is the position after the last element equal to
+/// `target`. Elements that are incomparable to `target` count as greater.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::search::upper_bound;
+///
+/// let nums = [1, 2, 2, 2, 5];
+/// assert_eq!(upper_bound(&nums, &2), 4);
+/// assert_eq!(upper_bound(&nums, &0), 0);
+/// assert_eq!(upper_bound(&nums, &5), 5);
+/// ```
+pub fn upper_bound<T>(nums: &[T], target: &T) -> usize
+where
+    T: PartialOrd,
+{
+    nums.partition_point(|x| x <= target)
+}
+
+/// Returns the range of the elements of `nums` that are equal to `target`.
+///
+/// `nums` must be sorted in ascending order. The range runs from [`lower_bound`] to
+/// [`upper_bound`]; if `target` is not present it is empty, and starts where `target`
+/// would be inserted.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::search::equal_range;
+///
+/// let nums = [1, 2, 2, 2, 5];
+/// assert_eq!(equal_range(&nums, &2), 1..4);
+/// assert_eq!(equal_range(&nums, &3), 4..4);
+/// assert!(equal_range(&nums, &3).is_empty());
+/// ```
+pub fn equal_range<T>(nums: &[T], target: &T) -> Range<usize>
+where
+    T: PartialOrd,
+{
+    let start = lower_bound(nums, target);
+    // the equal elements can only follow the lower bound
+    start..start + upper_bound(&nums[start..], target)
+}
+
+/// Inserts `x` into the ascending `nums` so that it stays sorted, and returns the index
+/// `x` was inserted at.
+///
+/// `x` goes after the elements equal to it, at its [`upper_bound`], so inserting equal
+/// elements keeps them in insertion order. Finding the place takes O(log n) comparisons;
+/// shifting the elements behind it takes O(n) moves, which is still far cheaper than
+/// sorting again for a sliding window.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::search::insert_sorted;
+///
+/// let mut window = vec![1, 3, 5];
+/// assert_eq!(insert_sorted(&mut window, 4), 2);
+/// assert_eq!(window, vec![1, 3, 4, 5]);
+/// ```
+pub fn insert_sorted<T>(nums: &mut Vec<T>, x: T) -> usize
+where
+    T: PartialOrd,
+{
+    let index = upper_bound(nums, &x);
+    nums.insert(index, x);
+    index
+}
+
+/// Removes one element equal to `x` from the ascending `nums`, keeping it sorted, and
+/// returns whether there was one.
+///
+/// The first of the equal elements, at the [`lower_bound`] of `x`, is removed. Like
+/// [`insert_sorted`], this takes O(log n) comparisons and O(n) moves.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::search::remove_sorted;
+///
+/// let mut window = vec![1, 3, 3, 5];
+/// assert!(remove_sorted(&mut window, &3));
+/// assert_eq!(window, vec![1, 3, 5]);
+/// assert!(!remove_sorted(&mut window, &4));
+/// ```
+pub fn remove_sorted<T>(nums: &mut Vec<T>, x: &T) -> bool
+where
+    T: PartialOrd,
+{
+    let index = lower_bound(nums, x);
+    if index < nums.len() && nums[index] == *x {
+        nums.remove(index);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_bounds_with_duplicate_runs() {
+        let nums = [1, 1, 3, 3, 3, 3, 7, 9, 9];
+        for target in 0..11 {
+            let expected_lower = nums.iter().filter(|&&x| x < target).count();
+            let expected_upper = nums.iter().filter(|&&x| x <= target).count();
+            assert_eq!(lower_bound(&nums, &target), expected_lower);
+            assert_eq!(upper_bound(&nums, &target), expected_upper);
+            assert_eq!(equal_range(&nums, &target), expected_lower..expected_upper);
+        }
+    }
+
+    #[test]
+    fn test_bounds_of_missing_targets() {
+        let nums = [2, 4, 6];
+        assert_eq!(equal_range(&nums, &1), 0..0);
+        assert_eq!(equal_range(&nums, &5), 2..2);
+        assert_eq!(equal_range(&nums, &7), 3..3);
+        assert_eq!(equal_range::<i32>(&[], &1), 0..0);
+    }
+
+    #[test]
+    fn test_bounds_of_constant_slice() {
+        let nums = [5; 8];
+        assert_eq!(equal_range(&nums, &5), 0..8);
+        assert_eq!(lower_bound(&nums, &6), 8);
+        assert_eq!(upper_bound(&nums, &4), 0);
+    }
+
+    #[test]
+    fn test_sliding_window_median() {
+        // a window of five over a pseudo-random stream, checked against sorting it anew
+        let stream = gen_few_unique(200, 50);
+        let mut window = Vec::new();
+        for (i, &x) in stream.iter().enumerate() {
+            insert_sorted(&mut window, x);
+            if i >= 5 {
+                assert!(remove_sorted(&mut window, &stream[i - 5]));
+            }
+            let mut expected = stream[i.saturating_sub(4)..=i].to_vec();
+            expected.sort();
+            assert_eq!(window, expected);
+        }
+    }
+
+    #[test]
+    fn test_insert_sorted_after_equal_elements() {
+        // compares by the first field only
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct First(i32, char);
+        impl PartialOrd for First {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+        let mut nums = alloc::vec![First(1, 'a'), First(2, 'a'), First(3, 'a')];
+        assert_eq!(insert_sorted(&mut nums, First(2, 'b')), 2);
+        assert_eq!(insert_sorted(&mut nums, First(2, 'c')), 3);
+        let tags: alloc::string::String = nums.iter().map(|x| x.1).collect();
+        assert_eq!(tags, "aabca");
+        assert!(!remove_sorted(&mut Vec::<i32>::new(), &1));
+    }
+
+    #[test]
+    fn test_insert_sorted_strings() {
+        use alloc::string::{String, ToString};
+
+        // the elements are moved in and compared by reference, so they need not be `Copy`
+        let mut words: Vec<String> = Vec::new();
+        for word in ["pear", "apple", "fig", "apple"] {
+            insert_sorted(&mut words, word.to_string());
+        }
+        assert_eq!(words, ["apple", "apple", "fig", "pear"]);
+        assert!(remove_sorted(&mut words, &"fig".to_string()));
+        assert!(!remove_sorted(&mut words, &"kiwi".to_string()));
+        assert_eq!(words, ["apple", "apple", "pear"]);
+    }
+}
@@ -0,0 +1,380 @@
+//! This module contains selection routines that avoid running the full sorting network.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::select::bitonic_top_k;
+//!
+//! let nums = [9, 4, 7, 1, 8, 2, 6];
+//! assert_eq!(bitonic_top_k(&nums, 3, 2), vec![1, 2, 4]);
+//! ```
+use crate::bitonic_serial;
+use crate::pool;
+use std::cmp::Ordering;
+use std::{mem, thread};
+
+// Windows this short are finished with a serial quickselect.
+const SERIAL_SELECT_LEN: usize = 1 << 14;
+
+/// Returns the `k` smallest elements of `nums` in ascending order.
+///
+/// The input is split into `parallel` partitions. Each thread selects the `k` smallest
+/// elements of its partition in O(n / parallel) and sorts only those, and the sorted
+/// candidates are then combined with the bitonic merge, keeping `k` elements after each
+/// step. This costs O(n + parallel * k log^2 k) comparisons, against O(n log^2 n) for
+/// sorting everything with `bitonic_sort` and slicing the first `k`. If `k` is larger
+/// than `nums.len()`, all elements are returned.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::select::bitonic_top_k;
+///
+/// let nums: Vec<u32> = (0..1000).rev().collect();
+/// assert_eq!(bitonic_top_k(&nums, 5, 4), vec![0, 1, 2, 3, 4]);
+/// ```
+pub fn bitonic_top_k<T>(nums: &[T], k: usize, parallel: usize) -> Vec<T>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    if nums.is_empty() || k == 0 {
+        return Vec::new();
+    }
+    let size = nums.len().div_ceil(parallel.max(1));
+    let runs: Vec<Vec<T>> = thread::scope(|s| {
+        let handles: Vec<_> = nums
+            .chunks(size)
+            .map(|chunk| {
+                pool::spawn_named(s, "bitonic-select", move || {
+                    let mut run = chunk.to_vec();
+                    if run.len() > k {
+                        run.select_nth_unstable_by(k - 1, __cmp);
+                        run.truncate(k);
+                    }
+                    bitonic_serial::bitonic_sort(&mut run);
+                    run
+                })
+            })
+            .collect();
+        handles.into_iter().map(pool::join).collect()
+    });
+    runs.into_iter()
+        .reduce(|a, b| {
+            let mut merged = bitonic_serial::bitonic_merge_sorted(&a, &b);
+            merged.truncate(k);
+            merged
+        })
+        .unwrap_or_default()
+}
+
+/// Reorders `nums` so that the element at index `n` is the one that would be there if
+/// `nums` were sorted, and returns it.
+///
+/// Every element before `n` is less than or equal to it and every element after `n` is
+/// greater than or equal to it, but neither side is sorted. Like `parallel_sort`, each
+/// step splits the current window into `parallel` partitions: the threads count how
+/// many elements of their partition fall below, on or above a sampled pivot, then
+/// scatter them into a scratch buffer at offsets computed from those counts. Only the
+/// part of the window that holds index `n` is kept for the next step, which takes
+/// O(n / parallel) expected time overall. Short windows are finished serially.
+///
+/// # Panics
+///
+/// Panics if `n >= nums.len()`.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::select::parallel_select_nth;
+///
+/// let mut nums = vec![9.0, 4.0, 7.0, 1.0, 8.0, 2.0, 6.0];
+/// let median = parallel_select_nth(&mut nums, 3, 4);
+/// assert_eq!(median, 6.0);
+/// assert!(nums[..3].iter().all(|&x| x <= median));
+/// assert!(nums[4..].iter().all(|&x| x >= median));
+/// ```
+pub fn parallel_select_nth<T>(nums: &mut [T], n: usize, parallel: usize) -> T
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    assert!(
+        n < nums.len(),
+        "index {} out of range for length {}",
+        n,
+        nums.len()
+    );
+    let parallel = parallel.max(1);
+    let mut scratch = Vec::new();
+    let (mut lo, mut hi) = (0, nums.len());
+    while parallel > 1 && hi - lo > SERIAL_SELECT_LEN {
+        let window = &mut nums[lo..hi];
+        let pivot = __sample_pivot(window);
+        if scratch.is_empty() {
+            scratch = window.to_vec();
+        }
+        let (less, equal) = __partition(window, &mut scratch[..hi - lo], pivot, parallel);
+        if n - lo < less {
+            hi = lo + less;
+        } else if n - lo < less + equal {
+            return nums[n];
+        } else {
+            lo += less + equal;
+        }
+    }
+    nums[lo..hi].select_nth_unstable_by(n - lo, __cmp);
+    nums[n]
+}
+
+/// Returns the smallest element of `nums`, or `None` if it is empty.
+///
+/// Each of `parallel` threads finds the minimum of its partition in one pass, and the
+/// per-partition results are combined on the calling thread. Of equal elements the
+/// first is returned. Incomparable elements such as `NaN` compare as equal to
+/// everything, so the result is unspecified when they are present.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::select::parallel_min;
+///
+/// assert_eq!(parallel_min(&[4, 2, 7, 1, 5], 2), Some(1));
+/// assert_eq!(parallel_min::<i32>(&[], 2), None);
+/// ```
+pub fn parallel_min<T>(nums: &[T], parallel: usize) -> Option<T>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    __reduce(nums, parallel, Ordering::Less)
+}
+
+/// Returns the largest element of `nums`, or `None` if it is empty.
+///
+/// This works like [`parallel_min`]. Of equal elements the first is returned.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::select::parallel_max;
+///
+/// assert_eq!(parallel_max(&[4, 2, 7, 1, 5], 2), Some(7));
+/// ```
+pub fn parallel_max<T>(nums: &[T], parallel: usize) -> Option<T>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    __reduce(nums, parallel, Ordering::Greater)
+}
+
+/// Returns the median of `nums`, or `None` if it is empty.
+///
+/// For an even number of elements this is the mean of the two middle ones, which is why
+/// the elements have to convert into `f64`. The upper middle element is found with
+/// [`parallel_select_nth`], which reorders `nums` in place; the lower one is then the
+/// largest element before it, found with [`parallel_max`].
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::select::parallel_median;
+///
+/// assert_eq!(parallel_median(&mut [9, 4, 7, 1, 8], 2), Some(7.0));
+/// assert_eq!(parallel_median(&mut [9, 4, 7, 1], 2), Some(5.5));
+/// ```
+pub fn parallel_median<T>(nums: &mut [T], parallel: usize) -> Option<f64>
+where
+    T: PartialOrd + Copy + Send + Sync + Into<f64>,
+{
+    if nums.is_empty() {
+        return None;
+    }
+    let mid = nums.len() / 2;
+    let upper: f64 = parallel_select_nth(nums, mid, parallel).into();
+    if nums.len() % 2 == 1 {
+        return Some(upper);
+    }
+    let lower: f64 = parallel_max(&nums[..mid], parallel)?.into();
+    Some((lower + upper) / 2.0)
+}
+
+// Returns the first element that no other element is `wanted` relative to, that is the
+// minimum for `Ordering::Less` and the maximum for `Ordering::Greater`.
+fn __reduce<T>(nums: &[T], parallel: usize, wanted: Ordering) -> Option<T>
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let best = |chunk: &[T]| {
+        chunk
+            .iter()
+            .copied()
+            .reduce(|best, x| if __cmp(&x, &best) == wanted { x } else { best })
+    };
+    if parallel <= 1 || nums.len() <= SERIAL_SELECT_LEN {
+        return best(nums);
+    }
+    let size = nums.len().div_ceil(parallel);
+    let candidates: Vec<T> = thread::scope(|s| {
+        let handles: Vec<_> = nums
+            .chunks(size)
+            .map(|chunk| pool::spawn_named(s, "bitonic-select", move || best(chunk)))
+            .collect();
+        handles.into_iter().filter_map(pool::join).collect()
+    });
+    best(&candidates)
+}
+
+// The median of up to 31 evenly spaced elements.
+fn __sample_pivot<T: PartialOrd + Copy>(nums: &[T]) -> T {
+    let step = (nums.len() / 31).max(1);
+    let mut sample: Vec<T> = nums.iter().step_by(step).take(31).copied().collect();
+    bitonic_serial::bitonic_sort(&mut sample);
+    sample[sample.len() / 2]
+}
+
+// Stably moves the elements below, equal to and above `pivot` to the front, middle and
+// back of `nums`, using `scratch` of the same length. Returns the sizes of the first
+// two groups.
+fn __partition<T>(nums: &mut [T], scratch: &mut [T], pivot: T, parallel: usize) -> (usize, usize)
+where
+    T: PartialOrd + Copy + Send + Sync,
+{
+    let size = nums.len().div_ceil(parallel);
+    let bucket = |x: &T| match __cmp(x, &pivot) {
+        Ordering::Less => 0,
+        Ordering::Equal => 1,
+        Ordering::Greater => 2,
+    };
+    let counts: Vec<[usize; 3]> = thread::scope(|s| {
+        let handles: Vec<_> = nums
+            .chunks(size)
+            .map(|chunk| {
+                pool::spawn_named(s, "bitonic-select", move || {
+                    let mut count = [0; 3];
+                    for x in chunk {
+                        count[bucket(x)] += 1;
+                    }
+                    count
+                })
+            })
+            .collect();
+        handles.into_iter().map(pool::join).collect()
+    });
+    let less: usize = counts.iter().map(|count| count[0]).sum();
+    let equal: usize = counts.iter().map(|count| count[1]).sum();
+    // each partition writes its groups right after those of the partitions before it
+    let (front, rest) = scratch.split_at_mut(less);
+    let (middle, back) = rest.split_at_mut(equal);
+    let mut groups = [front, middle, back];
+    thread::scope(|s| {
+        for (chunk, count) in nums.chunks(size).zip(&counts) {
+            let mut dest = [0, 1, 2].map(|group| {
+                let (part, rest) = mem::take(&mut groups[group]).split_at_mut(count[group]);
+                groups[group] = rest;
+                part.iter_mut()
+            });
+            pool::spawn_named(s, "bitonic-select", move || {
+                for x in chunk {
+                    *dest[bucket(x)].next().unwrap() = *x;
+                }
+            });
+        }
+    });
+    thread::scope(|s| {
+        for (to, from) in nums.chunks_mut(size).zip(scratch.chunks(size)) {
+            pool::spawn_named(s, "bitonic-select", move || to.copy_from_slice(from));
+        }
+    });
+    (less, equal)
+}
+
+fn __cmp<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_bitonic_top_k() {
+        let nums = [4, 2, 7, 1, 5, 3, 6];
+        assert_eq!(bitonic_top_k(&nums, 3, 2), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_bitonic_top_k_edge_cases() {
+        let nums = [4, 2, 7, 1];
+        assert_eq!(bitonic_top_k(&nums, 0, 2), vec![]);
+        assert_eq!(bitonic_top_k(&nums, 10, 2), vec![1, 2, 4, 7]);
+        assert_eq!(bitonic_top_k::<i32>(&[], 3, 2), vec![]);
+    }
+
+    #[test]
+    fn test_bitonic_top_k_matches_sort() {
+        let nums = gen_few_unique(1000, 100);
+        let mut expected = nums.clone();
+        expected.sort();
+        for parallel in [1, 3, 8] {
+            for k in [1, 10, 99, 500] {
+                assert_eq!(bitonic_top_k(&nums, k, parallel), expected[..k]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_select_nth() {
+        let nums = gen_few_unique(100_000, 5000);
+        let mut expected = nums.clone();
+        expected.sort();
+        for parallel in [1, 3, 8] {
+            for n in [0, 1, 49_999, 77_777, 99_999] {
+                let mut selected = nums.clone();
+                let nth = parallel_select_nth(&mut selected, n, parallel);
+                assert_eq!(nth, expected[n]);
+                assert_eq!(selected[n], nth);
+                assert!(selected[..n].iter().all(|&x| x <= nth));
+                assert!(selected[n + 1..].iter().all(|&x| x >= nth));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_parallel_select_nth_out_of_range() {
+        parallel_select_nth(&mut [1, 2, 3], 3, 2);
+    }
+
+    #[test]
+    fn test_parallel_min_max() {
+        let nums: Vec<u32> = gen_few_unique(100_000, 5000)
+            .into_iter()
+            .map(|x| x + 7)
+            .collect();
+        for parallel in [0, 1, 3, 8] {
+            assert_eq!(parallel_min(&nums, parallel), nums.iter().copied().min());
+            assert_eq!(parallel_max(&nums, parallel), nums.iter().copied().max());
+        }
+        assert_eq!(parallel_min::<u32>(&[], 4), None);
+        assert_eq!(parallel_max(&[3.5], 4), Some(3.5));
+    }
+
+    #[test]
+    fn test_parallel_median() {
+        for len in [1, 2, 7, 100, 100_001, 100_000] {
+            let nums = gen_few_unique(len, 5000);
+            let mut sorted = nums.clone();
+            sorted.sort();
+            let mid = sorted.len() / 2;
+            let expected = if len % 2 == 1 {
+                sorted[mid] as f64
+            } else {
+                (sorted[mid - 1] as f64 + sorted[mid] as f64) / 2.0
+            };
+            for parallel in [1, 8] {
+                let mut selected = nums.clone();
+                assert_eq!(parallel_median(&mut selected, parallel), Some(expected));
+            }
+        }
+        assert_eq!(parallel_median::<f32>(&mut [], 4), None);
+    }
+}
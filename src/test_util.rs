@@ -0,0 +1,71 @@
+//! Input generators and records shared by the unit tests.
+//!
+//! The generators mirror those of the benchmarks in `benches/common.rs`, but return
+//! `u32`s, which every test converts to its own element type. Like there, every one of
+//! them is deterministic, so a failing test fails the same way again.
+#![allow(dead_code)]
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+
+/// The seed of the tests' random inputs.
+pub(crate) const SEED: u64 = 0x5EED_B170_2C50;
+
+/// Returns `n` values drawn uniformly from all of `u32`, the same ones for the same
+/// `seed`.
+pub(crate) fn gen_uniform(n: usize, seed: u64) -> Vec<u32> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n).map(|_| rng.gen()).collect()
+}
+
+/// Returns `0, 1, ..., n - 1`.
+pub(crate) fn gen_sorted(n: usize) -> Vec<u32> {
+    (0..n as u32).collect()
+}
+
+/// Returns `n - 1, ..., 1, 0`.
+pub(crate) fn gen_reverse_sorted(n: usize) -> Vec<u32> {
+    (0..n as u32).rev().collect()
+}
+
+/// Returns `n` values drawn uniformly from `0..k`, seeded with [`SEED`].
+///
+/// Unlike the benchmarks' values, these stay below `k`, which the tests rely on for the
+/// number of duplicates, for keys of a small domain and for values with room to spare
+/// below `u32::MAX`.
+pub(crate) fn gen_few_unique(n: usize, k: u32) -> Vec<u32> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    (0..n).map(|_| rng.gen_range(0..k)).collect()
+}
+
+/// A record compared by its key only, tagged with its input position, so that records
+/// with equal keys can be told apart when checking stability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Tagged(pub(crate) u32, pub(crate) u32);
+
+impl PartialOrd for Tagged {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tagged {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+/// Returns `n` records with keys from [`gen_few_unique`] and their positions as tags.
+pub(crate) fn gen_tagged(n: usize, k: u32) -> Vec<Tagged> {
+    gen_few_unique(n, k)
+        .into_iter()
+        .zip(0..)
+        .map(|(key, tag)| Tagged(key, tag))
+        .collect()
+}
+
+/// Returns the tags of `records`, in order.
+pub(crate) fn tags(records: &[Tagged]) -> Vec<u32> {
+    records.iter().map(|record| record.1).collect()
+}
@@ -0,0 +1,73 @@
+//! This module helps pick a `parallel` value from the machine's CPU topology.
+//!
+//! The sorts are compare-and-swap loops that keep a core's execution units busy, so two
+//! hyperthreads sharing a physical core gain little over one, while paying for twice
+//! the threads and synchronization. `recommended_parallel` therefore sizes the work by
+//! physical cores rather than the logical ones reported by
+//! `std::thread::available_parallelism`.
+//!
+//! With the `num_cpus` feature, physical cores are counted by `num_cpus::get_physical`.
+//! Without it there is no portable way to tell them apart, and every logical core is
+//! assumed to be a physical one, which matches the `*_auto` sorts.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::{bitonic_parallel, topology};
+//!
+//! let mut nums = vec![5, 3, 8, 1, 9, 2];
+//! bitonic_parallel::bitonic_sort(&mut nums, topology::recommended_parallel());
+//! assert_eq!(nums, vec![1, 2, 3, 5, 8, 9]);
+//! ```
+use std::thread;
+
+/// Returns the number of logical cores, or 1 if it is unknown.
+pub fn logical_cores() -> usize {
+    thread::available_parallelism().map_or(1, |cores| cores.get())
+}
+
+/// Returns the number of physical cores, or 1 if it is unknown.
+///
+/// The count never exceeds `logical_cores`, which honors CPU affinity and quotas that
+/// `num_cpus::get_physical` does not see. Without the `num_cpus` feature this is the
+/// same as `logical_cores`.
+pub fn physical_cores() -> usize {
+    #[cfg(feature = "num_cpus")]
+    {
+        num_cpus::get_physical().clamp(1, logical_cores())
+    }
+    #[cfg(not(feature = "num_cpus"))]
+    {
+        logical_cores()
+    }
+}
+
+/// Returns the `parallel` value recommended for this machine: the largest power of two
+/// that does not exceed the number of physical cores.
+///
+/// Rounding down keeps every thread on a core of its own, where the sorts of
+/// [`bitonic_parallel`](crate::bitonic_parallel) would round a count like 6 up to 8
+/// threads. The merge sort of [`parallel_sort`](crate::parallel_sort) takes any count
+/// as it is and would sort 6 partitions on 6 threads.
+pub fn recommended_parallel() -> usize {
+    1 << physical_cores().ilog2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_physical_cores() {
+        assert!(physical_cores() >= 1);
+        assert!(physical_cores() <= logical_cores());
+    }
+
+    #[test]
+    fn test_recommended_parallel() {
+        let parallel = recommended_parallel();
+        assert!(parallel.is_power_of_two());
+        assert!(parallel <= physical_cores());
+        assert!(parallel * 2 > physical_cores());
+    }
+}
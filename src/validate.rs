@@ -0,0 +1,253 @@
+//! This module contains checks for the sequence shapes the sorting networks rely on.
+//!
+//! # Examples
+//!
+//! ```
+//! use bitonic_sort::validate::is_bitonic;
+//!
+//! assert!(is_bitonic(&[1, 4, 6, 3, 2]));
+//! assert!(!is_bitonic(&[1, 4, 2, 6]));
+//! ```
+use crate::bitonic_serial::__should_swap;
+use core::cmp::Ordering;
+
+/// Returns whether `nums` is a bitonic sequence.
+///
+/// A sequence is bitonic if it first monotonically increases and then monotonically
+/// decreases, or is a circular rotation of such a sequence. Equal neighbours never
+/// break the shape, so empty, single-element, constant and fully monotonic slices are
+/// all (trivially) bitonic. A slice of three or more elements containing an
+/// incomparable pair of neighbours, such as `NaN`, is not.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::validate::is_bitonic;
+///
+/// assert!(is_bitonic(&[5, 7, 8, 3, 1]));
+/// assert!(is_bitonic(&[3, 1, 5, 7, 8])); // rotation of the above
+/// assert!(is_bitonic::<i32>(&[]));
+/// assert!(!is_bitonic(&[1, 3, 2, 4]));
+/// ```
+pub fn is_bitonic<T>(nums: &[T]) -> bool
+where
+    T: PartialOrd,
+{
+    let len = nums.len();
+    if len <= 2 {
+        return true;
+    }
+    // count the direction changes of the sequence read as a cycle; a bitonic one has at
+    // most two, the peak and the valley
+    let (mut first, mut prev) = (None, None);
+    let mut changes = 0;
+    for i in 0..len {
+        match nums[i].partial_cmp(&nums[(i + 1) % len]) {
+            None => return false,
+            Some(Ordering::Equal) => {}
+            Some(dir) => {
+                if first.is_none() {
+                    first = Some(dir);
+                }
+                if prev.is_some_and(|prev| prev != dir) {
+                    changes += 1;
+                }
+                prev = Some(dir);
+            }
+        }
+    }
+    if prev != first {
+        changes += 1;
+    }
+    changes <= 2
+}
+
+/// Returns whether `nums` is sorted in ascending order.
+///
+/// Every element must be less than or equal to its successor, so a slice containing an
+/// incomparable pair of neighbours, such as `NaN`, is not sorted. This is the oracle the
+/// crate's own tests check the sorts against.
+///
+/// # Examples
+///
+/// ```
+/// use bitonic_sort::validate::is_sorted;
+///
+/// assert!(is_sorted(&[1, 2, 2, 5]));
+/// assert!(is_sorted::<i32>(&[]));
+/// assert!(!is_sorted(&[1, 3, 2]));
+/// assert!(!is_sorted(&[1.0, f64::NAN, 2.0]));
+/// ```
+pub fn is_sorted<T>(nums: &[T]) -> bool
+where
+    T: PartialOrd,
+{
+    nums.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+// Debug builds call this at the end of the sorts whose comparator is a total order, to
+// catch a network that leaves neighbours it would still swap. Comparators that map
+// incomparable pairs to `Equal` are not transitive and may legitimately leave such
+// neighbours behind, so the sorts using them are not checked.
+pub(crate) fn __debug_assert_sorted<T, F>(nums: &[T], reverse: bool, cmp: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    debug_assert!(
+        !nums
+            .windows(2)
+            .any(|pair| __should_swap(cmp(&pair[0], &pair[1]), reverse)),
+        "the sort left its output out of order"
+    );
+}
+
+// The number of element triples `__debug_assert_total_order` checks.
+const TOTAL_ORDER_SAMPLES: usize = 256;
+
+// Debug builds call this before the sorts that take an untrusted comparator. It samples
+// triples of positions, pseudo-randomly but the same on every run, and panics if `cmp`
+// is not reflexive, antisymmetric or transitive on them, naming the positions; `T` need
+// not be `Debug`. A comparator can still be wrong on triples that were not sampled.
+pub(crate) fn __debug_assert_total_order<T, F>(nums: &[T], cmp: &F)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    if !cfg!(debug_assertions) || nums.is_empty() {
+        return;
+    }
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut index = || {
+        // xorshift, reduced to a position
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % nums.len() as u64) as usize
+    };
+    for _ in 0..TOTAL_ORDER_SAMPLES.min(nums.len().saturating_mul(nums.len())) {
+        let (a, b, c) = (index(), index(), index());
+        let (ab, bc, ac) = (
+            cmp(&nums[a], &nums[b]),
+            cmp(&nums[b], &nums[c]),
+            cmp(&nums[a], &nums[c]),
+        );
+        assert!(
+            cmp(&nums[a], &nums[a]) == Ordering::Equal,
+            "the comparator is not a total order: an element at {} is not equal to itself",
+            a
+        );
+        assert!(
+            cmp(&nums[b], &nums[a]) == ab.reverse(),
+            "the comparator is not a total order: comparing the elements at {} and {} \
+             gives {:?}, but comparing them the other way round does not give the reverse",
+            a,
+            b,
+            ab
+        );
+        // `a <= b <= c` implies `a <= c`, strictly if either step is strict, and
+        // likewise for `>=`
+        let transitive = match (ab, bc) {
+            (Ordering::Greater, Ordering::Less) | (Ordering::Less, Ordering::Greater) => true,
+            (Ordering::Greater, _) | (_, Ordering::Greater) => ac == ab.max(bc),
+            _ => ac == ab.min(bc),
+        };
+        assert!(
+            transitive,
+            "the comparator is not a total order: the elements at {}, {} and {} compare as \
+             {:?} and {:?}, but the first and the last as {:?}",
+            a, b, c, ab, bc, ac
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::gen_few_unique;
+
+    #[test]
+    fn test_is_sorted() {
+        assert!(is_sorted::<i32>(&[]));
+        assert!(is_sorted(&[7]));
+        assert!(is_sorted(&[1, 1, 2, 3, 3]));
+        assert!(!is_sorted(&[2, 1]));
+        assert!(!is_sorted(&[f64::NAN, f64::NAN]));
+    }
+
+    #[test]
+    fn test_is_bitonic_trivial() {
+        assert!(is_bitonic::<i32>(&[]));
+        assert!(is_bitonic(&[42]));
+        assert!(is_bitonic(&[2, 1]));
+        assert!(is_bitonic(&[3, 3, 3, 3]));
+    }
+
+    #[test]
+    fn test_is_bitonic_monotonic() {
+        assert!(is_bitonic(&[1, 2, 3, 4, 5]));
+        assert!(is_bitonic(&[5, 4, 3, 2, 1]));
+        assert!(is_bitonic(&[1, 1, 2, 2, 3]));
+    }
+
+    #[test]
+    fn test_is_bitonic_increasing_then_decreasing() {
+        assert!(is_bitonic(&[1, 4, 6, 6, 3, 2]));
+        assert!(is_bitonic(&[6, 3, 2, 1, 4, 5]));
+        assert!(is_bitonic(&[3, 2, 1, 4, 6, 5]));
+    }
+
+    #[test]
+    fn test_is_bitonic_rejects() {
+        assert!(!is_bitonic(&[1, 3, 2, 4]));
+        assert!(!is_bitonic(&[1, 5, 2, 6, 3]));
+        assert!(!is_bitonic(&[1.0, f64::NAN, 2.0]));
+    }
+
+    #[test]
+    fn test_debug_assert_sorted() {
+        __debug_assert_sorted(&[1, 2, 2, 3], false, &i32::cmp);
+        __debug_assert_sorted(&[3, 2, 2, 1], true, &i32::cmp);
+        __debug_assert_sorted::<i32, _>(&[], false, &i32::cmp);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "out of order")]
+    fn test_debug_assert_sorted_unsorted() {
+        __debug_assert_sorted(&[1, 3, 2], false, &i32::cmp);
+    }
+
+    #[test]
+    fn test_debug_assert_total_order() {
+        let nums = gen_few_unique(100, 20);
+        __debug_assert_total_order(&nums, &u32::cmp);
+        __debug_assert_total_order(&nums, &|a: &u32, b: &u32| b.cmp(a));
+        __debug_assert_total_order(&nums[..1], &u32::cmp);
+        __debug_assert_total_order::<u32, _>(&[], &u32::cmp);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a total order")]
+    fn test_debug_assert_total_order_asymmetric() {
+        // claims every element is less than every other
+        __debug_assert_total_order(&[1, 2, 3], &|a: &i32, b: &i32| match a == b {
+            true => Ordering::Equal,
+            false => Ordering::Less,
+        });
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not a total order")]
+    fn test_debug_assert_total_order_intransitive() {
+        // rock, paper, scissors: each beats the next one round the circle
+        __debug_assert_total_order(
+            &[0, 1, 2],
+            &|a: &i32, b: &i32| match (b - a).rem_euclid(3) {
+                0 => Ordering::Equal,
+                1 => Ordering::Less,
+                _ => Ordering::Greater,
+            },
+        );
+    }
+}